@@ -1,28 +1,38 @@
 pub mod unit;
 pub use unit::*;
+mod platform;
+mod sound_command;
+mod sound_fade;
+mod sound_history;
+mod sound_loop;
+mod sound_stretch;
+mod sound_tempo;
+mod sound_time;
+use platform::{bump_priority_worker_thread, core_count, pin_to_core};
+pub use sound_command::*;
+pub use sound_fade::*;
+pub use sound_history::*;
+pub use sound_loop::*;
+pub use sound_stretch::*;
+pub use sound_tempo::*;
+pub use sound_time::*;
+mod sound_layout;
+pub use sound_layout::*;
 mod sound_track_update;
 pub use sound_track_update::*;
+mod sound_export;
+#[cfg(feature = "cpal-output")]
+mod sound_output;
 mod sound_play;
 use crossbeam_utils::atomic::AtomicCell;
+pub use sound_export::*;
+#[cfg(feature = "cpal-output")]
+pub use sound_output::*;
 pub use sound_play::*;
 use std::collections::BTreeMap;
 use std::os::raw::c_char;
-use std::sync::Condvar;
-
-fn bump_priority_worker_thread() {
-    unsafe {
-        let h = GetCurrentThread(); //Windows API 핸들 얻기
-        let _ = SetThreadPriority(h, THREAD_PRIORITY_ABOVE_NORMAL); //우선순위 올리기
-    }
-}
-
-fn pin_to_core(core_id: usize) {
-    if let Some(core) = core_affinity::get_core_ids().and_then(|v| v.get(core_id).cloned())
-    //코어 ID 유효성 검사
-    {
-        let _ = core_affinity::set_for_current(core); //현재 스레드를 해당 코어에 고정
-    }
-}
+use std::sync::RwLock;
+use std::thread::Thread;
 
 pub struct CircularBuffer {
     producer: Option<Producer<f32>>, //디코더/프로듀서가 push할 핸들
@@ -37,8 +47,11 @@ pub struct TrackConfig {
 }
 impl TrackConfig {
     fn new() -> Result<Self, String> {
-        let (tx, rx) = RingBuffer::<f32>::new(slots(RB1_FRAMES));
-        //f32 타입에 배열을 생성 [CAPACITY_SAMPLES] 만큼
+        Self::with_ring_frames(ring_frames_for(DEFAULT_RING_MS, 48_000))
+    }
+    fn with_ring_frames(ring_frames: usize) -> Result<Self, String> {
+        let (tx, rx) = RingBuffer::<f32>::new(slots(ring_frames));
+        //f32 타입에 배열을 생성 [ring_frames * CHANNELS] 만큼
         let circularbuffer = CircularBuffer {
             producer: Some(tx),
             consumer: Some(rx),
@@ -47,47 +60,233 @@ impl TrackConfig {
             volume: 0.5,
             muted: false,
             pan: 0.0,
-            circularbuffer: circularbuffer,
+            circularbuffer,
         })
     }
 }
 
 pub struct Parameters {
-    volume: Vec<AtomicU32>,
-    pan: Vec<AtomicU32>,
-    muted: Vec<AtomicBool>,
-    bpm: AtomicU32,
+    tempo: RwLock<Arc<TempoMap>>, //기본 BPM + 템포 이벤트 (워커는 스냅샷만 씀, 바꾸면 리버퍼)
 }
 impl Parameters {
-    fn from_tracks(track: &Vec<TrackConfig>) -> Self {
-        let volume = track
-            .iter()
-            .map(|t| AtomicU32::new(t.volume.to_bits()))
-            .collect();
-        let pan = track
-            .iter()
-            .map(|t| AtomicU32::new(t.pan.to_bits()))
-            .collect();
-        let muted = track.iter().map(|t| AtomicBool::new(t.muted)).collect();
-        let bpm = AtomicU32::new((60.0f32).to_bits());
+    fn new() -> Self {
+        Self {
+            tempo: RwLock::new(Arc::new(TempoMap::new(BASE_BPM, Vec::new()))),
+        }
+    }
+    fn tempo(&self) -> Arc<TempoMap> {
+        match self.tempo.read() {
+            Ok(g) => Arc::clone(&g),
+            Err(p) => Arc::clone(&p.into_inner()),
+        }
+    }
+    fn set_tempo(&self, map: TempoMap) {
+        match self.tempo.write() {
+            Ok(mut g) => *g = Arc::new(map),
+            Err(p) => *p.into_inner() = Arc::new(map),
+        }
+    }
+}
+
+pub struct TrackParams {
+    volume: AtomicU32,
+    pan: AtomicU32,
+    muted: AtomicBool,
+    route: AtomicI32, //-1 = 메인(레이아웃 패닝), 0 이상 = 그 채널부터 L/R 직접 출력
+    azimuth: AtomicU32, //서라운드 레이아웃에서 트랙 방향 (도, 0 = 정면)
+}
+impl TrackParams {
+    fn from_track(track: &TrackConfig) -> Self {
+        Self {
+            volume: AtomicU32::new(track.volume.to_bits()),
+            pan: AtomicU32::new(track.pan.to_bits()),
+            muted: AtomicBool::new(track.muted),
+            route: AtomicI32::new(-1),
+            azimuth: AtomicU32::new(0.0f32.to_bits()),
+        }
+    }
+    // 뮤트/볼륨 0이면 None, 아니면 (L, R) 게인
+    fn gains(&self) -> Option<(f32, f32)> {
+        let muted = self.muted.load(Ordering::Relaxed);
+        let vol = f32::from_bits(self.volume.load(Ordering::Relaxed)).clamp(0.0, 1.0);
+        let pan = f32::from_bits(self.pan.load(Ordering::Relaxed)).clamp(-1.0, 1.0);
+        if muted || vol == 0.0 {
+            return None;
+        }
+        // 간단한 equal-power가 아닌 linear-pan (요청 내용 유지)
+        Some((vol * (1.0 - pan) * 0.5, vol * (1.0 + pan) * 0.5))
+    }
+    // 믹서 값 하나를 바로 바꾸고 이전 값을 돌려줌 (렌더는 다음 콜백부터 읽음)
+    fn swap_mixer(&self, value: MixerParam) -> MixerParam {
+        match value {
+            MixerParam::Volume(v) => MixerParam::Volume(f32::from_bits(
+                self.volume.swap(v.to_bits(), Ordering::Relaxed),
+            )),
+            MixerParam::Pan(p) => {
+                MixerParam::Pan(f32::from_bits(self.pan.swap(p.to_bits(), Ordering::Relaxed)))
+            }
+            MixerParam::Mute(m) => MixerParam::Mute(self.muted.swap(m, Ordering::Relaxed)),
+            MixerParam::Route(ch) => MixerParam::Route(self.route.swap(ch, Ordering::Relaxed)),
+            MixerParam::Azimuth(a) => MixerParam::Azimuth(f32::from_bits(
+                self.azimuth.swap(a.to_bits(), Ordering::Relaxed),
+            )),
+        }
+    }
+}
+
+// 트랙 믹서 값 (편집 큐를 안 타고 바로 반영, 되돌리기 기록만 남김)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MixerParam {
+    Volume(f32),
+    Pan(f32),
+    Mute(bool),
+    Route(i32), //-1 = 메인
+    Azimuth(f32),
+}
+
+// 트랙 하나가 가진 실행 상태 전부 (링버퍼/타임라인/디코더/파라미터)
+pub struct TrackSlot {
+    id: u64,                        //엔진 안에서 고유 (순서가 바뀌어도 유지)
+    producer: Mutex<Producer<f32>>, //컨슈머는 렌더 스레드가 소유 (RenderTrack)
+    timeline: Mutex<TrackTimeline>,
+    voices: Mutex<TrackVoices>, //클립 디코더들 (겹친 클립마다 하나)
+    params: TrackParams,
+    refilling: AtomicBool, //워터마크 히스테리시스: low 밑이면 true, high 넘으면 false
+}
+impl TrackSlot {
+    fn from_config(mut tk: TrackConfig, id: u64, write_pos_frames: u64) -> (Self, Consumer<f32>) {
+        let tx = match tk.circularbuffer.producer.take() {
+            Some(tx) => tx,
+            None => panic!("[TrackSlot] producer already taken (TrackConfig 재사용 가능성)"),
+        };
+        let rx = match tk.circularbuffer.consumer.take() {
+            Some(rx) => rx,
+            None => panic!("[TrackSlot] consumer already taken (TrackConfig 재사용 가능성)"),
+        };
+        let slot = Self {
+            id,
+            producer: Mutex::new(tx),
+            timeline: Mutex::new(TrackTimeline {
+                clips: BTreeMap::new(),
+                write_pos_frames,
+            }),
+            voices: Mutex::new(TrackVoices::new()),
+            params: TrackParams::from_track(&tk),
+            refilling: AtomicBool::new(true),
+        };
+        (slot, rx)
+    }
+}
+
+// 트랙 목록: 읽는 쪽(렌더/워커)은 스냅샷(Arc 복사)만 잡고, 편집은 복사 후 교체
+pub struct TrackList {
+    slots: RwLock<Arc<Vec<Arc<TrackSlot>>>>,
+}
+impl TrackList {
+    fn new(slots: Vec<Arc<TrackSlot>>) -> Self {
         Self {
-            volume,
-            pan,
-            muted,
-            bpm,
+            slots: RwLock::new(Arc::new(slots)),
+        }
+    }
+    fn snapshot(&self) -> Arc<Vec<Arc<TrackSlot>>> {
+        match self.slots.read() {
+            Ok(g) => Arc::clone(&g),
+            Err(p) => Arc::clone(&p.into_inner()),
         }
     }
+    fn len(&self) -> usize {
+        self.snapshot().len()
+    }
+    fn get(&self, idx: usize) -> Option<Arc<TrackSlot>> {
+        self.snapshot().get(idx).cloned()
+    }
+    fn find(&self, id: u64) -> Option<Arc<TrackSlot>> {
+        self.snapshot().iter().find(|s| s.id == id).cloned()
+    }
+    fn update<R>(&self, f: impl FnOnce(&mut Vec<Arc<TrackSlot>>) -> R) -> R {
+        let mut g = match self.slots.write() {
+            Ok(g) => g,
+            Err(p) => p.into_inner(),
+        };
+        let mut next: Vec<Arc<TrackSlot>> = g.as_ref().clone(); //복사본 수정 후 교체
+        let r = f(&mut next);
+        *g = Arc::new(next);
+        r
+    }
 }
 
+#[derive(Clone)]
 pub struct Clip {
+    id: u64, //엔진이 붙인 클립 id (엔진 안에서 고유, 트랙을 옮겨도 유지)
     file_path: String,
     src_sr: u32,
     tl_start: u64,
     tl_len: u64,
+    tl_offset: u64, //앞에서 잘려 나간 타임라인 프레임 (분할한 뒷조각), 소스 위치는 여기부터
+    src_start: u64, //소스 파일에서 재생을 시작할 위치 (소스 샘플레이트 기준 프레임)
+    src_end: u64,   //여기서 소스 재생 끝 (0 = 파일 끝까지), 남은 클립 구간은 무음
+    fade_in: Fade,  //클립 시작에서 올라오는 페이드
+    fade_out: Fade, //클립 끝(tl_len)에서 내려가는 페이드
+    gain_db: f32,   //클립 게인 (트랙 볼륨 앞단)
+    invert: bool,   //극성 반전
+    loop_len: u64,  //반복 길이 (타임라인 프레임, 0 = 반복 안 함)
+    loop_count: u32, //반복 횟수 (0 = tl_len 끝까지), 다 돌면 나머지는 무음
+    reversed: bool, //소스 구간을 끝에서부터 거꾸로 재생
+    tempo_mode: TempoMode, //템포가 60이 아닐 때 리샘플(피치 변함) / 스트레치(피치 유지)
+    pitch_cents: f32, //피치 (cent, 0 = 원래 음)
+    pitch_mode: PitchMode, //피치를 바꿀 때 길이도 같이 바꿀지
+    anchor: Option<BeatAnchor>, //박 고정 (Some이면 위치/길이는 틱이 기준, 템포가 바뀌면 다시 계산)
+}
+impl Clip {
+    // 소스 구간을 그대로 까는 새 클립 (페이드/게인/반복/피치 없음), 나머지는 편집 명령으로 바꿈
+    pub fn new(
+        id: u64,
+        file_path: String,
+        src_sr: u32,
+        tl_start: u64,
+        tl_len: u64,
+        src_start: u64,
+        src_end: u64,
+    ) -> Self {
+        Self {
+            id,
+            file_path,
+            src_sr,
+            tl_start,
+            tl_len,
+            tl_offset: 0,
+            src_start,
+            src_end,
+            fade_in: Fade::default(),
+            fade_out: Fade::default(),
+            gain_db: 0.0,
+            invert: false,
+            loop_len: 0,
+            loop_count: 0,
+            reversed: false,
+            tempo_mode: TempoMode::Resample,
+            pitch_cents: 0.0,
+            pitch_mode: PitchMode::Varispeed,
+            anchor: None,
+        }
+    }
 }
 pub struct TrackTimeline {
-    clips: BTreeMap<u64, Clip>, //시작시간,클립
-    write_pos_frames: u64,      //현재 재생 위치
+    clips: BTreeMap<ClipKey, Clip>, //(시작시간, id),클립
+    write_pos_frames: u64,          //다음에 쓸 위치 (스트림 좌표, 반복 구간을 펼친 값)
+}
+// 타임라인 정렬 키: 같은 프레임에서 시작하는 클립도 id로 구분
+pub type ClipKey = (u64, u64);
+
+// 클립 하나를 맡은 디코더 (clip_id: 마지막으로 맡은 클립)
+pub struct ClipVoice {
+    clip_id: u64,
+    dec: DecoderState,
+}
+// 트랙 하나의 디코더 묶음: 겹친 클립은 보이스마다 따로 디코드해서 mix에 더함
+pub struct TrackVoices {
+    voices: Vec<ClipVoice>,
+    mix: Vec<f32>, //구간 믹스 버퍼 (재사용)
 }
 pub struct DecoderState {
     format: Box<dyn symphonia::core::formats::FormatReader>,
@@ -95,13 +294,29 @@ pub struct DecoderState {
     sample_buf: SampleBuffer<f32>,
     src_sr: u32,
     src_pos_samples: u64,
+    src_len: u64, //소스 전체 프레임 수 (0 = 모름)
     file_path: String,
+    window: SourceWindow, //역재생/스트레치용으로 디코드해 둔 소스 구간
+    stretch: Stretcher,   //스트레치 상태 (출력이 이어지는 동안 유지)
+}
+
+// 디코드해 둔 소스 구간 (from부터 L/R)
+// 다음 요청이 창 안이면 다시 디코드하지 않고, 창 끝에 이어지면 디코더를 앞으로 더 읽어 늘림
+#[derive(Default)]
+pub struct SourceWindow {
+    buf: Vec<f32>,
+    from: u64,
+    cursor: Option<(usize, usize)>, //창 끝에서 이어 읽을 샘플버퍼 위치/채널 수 (None = 다음에 시킹부터)
+    eof: bool,                      //창 끝이 파일 끝
 }
 
 pub struct Transport {
     playing: AtomicBool,
-    playhead_frames: AtomicU64,
+    playhead_frames: AtomicU64, //스트림 위치 (반복 구간을 펼친 좌표, 렌더는 더하기만 함)
     sample_rate: AtomicU32,
+    loop_region: AtomicCell<LoopRegion>, //설정된 반복 구간 (꺼도 값은 기억)
+    loop_enabled: AtomicBool,
+    loop_run: AtomicCell<LoopRegion>, //지금 실제로 도는 구간 (seek 때 정함, 구간 뒤에서 시작하면 NONE)
 }
 impl Transport {
     fn new(sr: u32) -> Self {
@@ -109,6 +324,9 @@ impl Transport {
             playing: AtomicBool::new(false),
             playhead_frames: AtomicU64::new(0),
             sample_rate: AtomicU32::new(sr),
+            loop_region: AtomicCell::new(LoopRegion::NONE),
+            loop_enabled: AtomicBool::new(false),
+            loop_run: AtomicCell::new(LoopRegion::NONE),
         }
     }
     fn set_sr(&self, sr: u32) {
@@ -133,15 +351,55 @@ impl Transport {
     }
     fn seek_frames(&self, s: u64) {
         //재생 위치를 s로 이동
+        self.loop_run.store(self.loop_run_from(s));
         self.playhead_frames.store(s, Ordering::Relaxed);
     }
+    fn loop_run_from(&self, s: u64) -> LoopRegion {
+        //s에서 재생을 시작하면 돌 구간 (반복 구간 끝 앞이면 그 구간, 뒤면 반복 없이)
+        let region = self.loop_region.load();
+        if self.loop_enabled() && region.is_active() && s < region.end {
+            region
+        } else {
+            LoopRegion::NONE
+        }
+    }
     fn pos_frames(&self) -> u64 {
-        //현재 재생 위치
+        //현재 재생 위치 (타임라인)
+        self.loop_run.load().fold(self.stream_frames())
+    }
+    fn stream_frames(&self) -> u64 {
+        //반복을 펼친 재생 위치
         self.playhead_frames.load(Ordering::Relaxed)
     }
-    fn advance_frames(&self, s: u64) {
-        //재생 위치를 s만큼 증가
-        self.playhead_frames.fetch_add(s, Ordering::Relaxed);
+    fn playhead(&self) -> Playhead {
+        Playhead {
+            stream: self.stream_frames(),
+            looped: self.loop_run.load(),
+        }
+    }
+    fn set_loop(&self, region: LoopRegion, enabled: bool) {
+        //다음 seek부터 반영
+        self.loop_region.store(region);
+        self.loop_enabled.store(enabled, Ordering::Relaxed);
+    }
+    fn loop_region(&self) -> LoopRegion {
+        self.loop_region.load()
+    }
+    fn loop_run(&self) -> LoopRegion {
+        self.loop_run.load()
+    }
+    fn set_loop_run(&self, run: LoopRegion) {
+        //seek 없이 도는 구간만 바꿈 (호출한 쪽이 끊김 없는지 확인)
+        self.loop_run.store(run);
+    }
+    fn loop_enabled(&self) -> bool {
+        self.loop_enabled.load(Ordering::Relaxed)
+    }
+    fn advance_from(&self, from: u64, s: u64) -> bool {
+        //재생 위치를 from에서 s만큼 증가 (그 사이 seek가 끼어들었으면 건드리지 않음)
+        self.playhead_frames
+            .compare_exchange(from, from + s, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
     }
 }
 
@@ -155,320 +413,390 @@ struct SfxState {
     frame: usize, // 현재 재생 위치 0..nframes
 }
 
+// -------------------------
+// 렌더 스레드 전용 상태: 락 없이 오디오 콜백만 만진다
+// -------------------------
+struct RenderTrack {
+    slot: Arc<TrackSlot>,
+    consumer: Consumer<f32>,
+}
+
+// 컨트롤 → 렌더
+enum RenderMsg {
+    AddTrack(RenderTrack),
+    RemoveTrack(u64),
+    PadOn(Arc<Sample>),
+    PadOff,
+}
+
+// 컨트롤 → 렌더 리버퍼 (큐가 아니라 한 칸짜리 슬롯: 꽉 차서 잃어버리는 일이 없고 최신 것만 남음)
+struct Rebuffer {
+    epoch: u64,
+    consumers: Vec<(u64, Consumer<f32>)>, //트랙 id, 새 컨슈머
+}
+type RebufferSlot = AtomicCell<Option<Box<Rebuffer>>>;
+const _: () = assert!(RebufferSlot::is_lock_free()); //렌더가 락 없이 take
+
+// 렌더 → 컨트롤 (할당 해제는 오디오 스레드 밖에서, 받는 쪽은 드롭만 함)
+#[allow(dead_code)]
+enum RenderGarbage {
+    Track(RenderTrack),
+    Rebuffer(Box<Rebuffer>),
+    Sfx(SfxState),
+}
+
+struct RenderState {
+    tracks: Vec<RenderTrack>, //MAX_TRACKS 만큼 미리 확보 (콜백 안에서 재할당 없음)
+    sfx: Option<SfxState>,
+    epoch: u64, //지금 컨슈머들이 속한 seek 에포크
+    inbox: Consumer<RenderMsg>,
+    rebuffer: Arc<RebufferSlot>,
+    trash: Producer<RenderGarbage>,
+}
+
 pub struct Engine {
-    producers: Arc<Vec<Mutex<Producer<f32>>>>,
-    consumers: Arc<Vec<Mutex<Consumer<f32>>>>,
+    tracks: Arc<TrackList>,
 
     thread_worker: Vec<JoinHandle<()>>,
-    thread_stop: Arc<AtomicBool>,             //스레드 종료
-    thread_wait: Arc<(Mutex<bool>, Condvar)>, //전체 대기
+    worker_threads: Vec<Thread>, //unpark용 핸들 (트랙 id % 개수 = 담당 워커)
+    thread_stop: Arc<AtomicBool>, //스레드 종료
+    thread_paused: Arc<AtomicBool>, //전체 대기
 
     real_time_params: Arc<Parameters>,
-    track_run_time: Arc<Vec<Mutex<TrackTimeline>>>,
-    decod: Arc<Vec<Mutex<Option<DecoderState>>>>,
 
     play_time_manager: Arc<Transport>,
-    seek_epoch: Arc<AtomicU64>,
-    track: Vec<TrackConfig>,
-    seek_lock: Arc<Mutex<()>>,
+    seek_epoch: Arc<AtomicU64>, //렌더가 자기 에포크와 다르면 리버퍼 중으로 보고 무음
+    seek_lock: Arc<Mutex<()>>,  //컨트롤 스레드끼리만 (렌더는 안 잡음)
+    rebuffer: Arc<RebufferSlot>, //렌더가 아직 안 가져간 최신 리버퍼
     pending_bpm: AtomicU32,
     has_pending_bpm: AtomicBool,
     pad_sample: AtomicCell<Option<Arc<Sample>>>,
-    sfx_state: Mutex<Option<SfxState>>,
-    underrun_callbacks: AtomicU64, // 콜백 단위 XRUN
-    underrun_samples: AtomicU64,   // 0.0로 때운 샘플 수(채널단위)
+    next_track_id: AtomicU64,
+    next_clip_id: AtomicU64,          //0은 실패 표시로 쓰므로 1부터
+    ring_ms: AtomicU32,               //트랙 링버퍼 지연 목표 (다음 리버퍼부터 반영)
+    out_layout: AtomicU32,            //SpeakerLayout 코드 (렌더가 콜백마다 읽음)
+    meter: AtomicCell<TimeSignature>, //박자표 (재생과 무관, 음악 시간 변환용)
+    edits: Arc<EditQueue>,            //타임라인/믹서 편집은 전부 이 큐를 거침
+
+    render: RenderState,                          //오디오 콜백 전용
+    render_tx: Mutex<Producer<RenderMsg>>,        //컨트롤 → 렌더
+    render_trash: Mutex<Consumer<RenderGarbage>>, //렌더 → 컨트롤
+    underrun_callbacks: AtomicU64,                // 콜백 단위 XRUN
+    underrun_samples: AtomicU64,                  // 0.0로 때운 샘플 수(채널단위)
+    host_render: AtomicBool, // JUCE 콜백이 render_channels를 돌리는 중 (cpal 출력과 같이 못 씀)
+    #[cfg(feature = "cpal-output")]
+    output: Option<CpalOutput>, // 엔진이 직접 연 cpal 출력 (JUCE 없이 구동할 때)
 }
 impl Engine {
-    fn new(mut tk: Vec<TrackConfig>) -> Self {
-        // 1) 1차 링버퍼 소유권: TrackConfig에서 꺼내 Engine이 보관
-        let mut prod_vec = Vec::with_capacity(tk.len());
-        let mut cons_vec = Vec::with_capacity(tk.len());
-        for tks in &mut tk {
-            let tx = match tks.circularbuffer.producer.take() {
-                Some(tx) => tx,
-                None => panic!("[Engine::new] producer already taken (TrackConfig 재사용 가능성)"),
-            };
-            let rx = match tks.circularbuffer.consumer.take() {
-                Some(rx) => rx,
-                None => panic!("[Engine::new] consumer already taken (TrackConfig 재사용 가능성)"),
-            };
-            prod_vec.push(Mutex::new(tx));
-            cons_vec.push(Mutex::new(rx));
+    pub fn with_track_count(n: usize) -> Result<Self, String> {
+        Self::with_track_count_and_latency(n, DEFAULT_RING_MS)
+    }
+
+    pub fn with_track_count_and_latency(n: usize, ring_ms: u32) -> Result<Self, String> {
+        let ring_frames = ring_frames_for(ring_ms, 48_000);
+        let mut tk = Vec::with_capacity(n);
+        for _ in 0..n {
+            tk.push(TrackConfig::with_ring_frames(ring_frames)?);
+        }
+        let eng = Self::new(tk);
+        eng.ring_ms.store(ring_ms, Ordering::Relaxed);
+        Ok(eng)
+    }
+
+    fn new(tk: Vec<TrackConfig>) -> Self {
+        // 1) 1차 링버퍼 소유권: 프로듀서는 트랙 슬롯, 컨슈머는 렌더 상태가 보관
+        let n_tracks = tk.len() as u64;
+        let mut slots: Vec<Arc<TrackSlot>> = Vec::with_capacity(tk.len());
+        let mut render_tracks: Vec<RenderTrack> = Vec::with_capacity(MAX_TRACKS.max(tk.len()));
+        for (id, t) in tk.into_iter().enumerate() {
+            let (slot, consumer) = TrackSlot::from_config(t, id as u64, 0);
+            let slot = Arc::new(slot);
+            render_tracks.push(RenderTrack {
+                slot: Arc::clone(&slot),
+                consumer,
+            });
+            slots.push(slot);
         }
-        let producers = Arc::new(prod_vec);
-        let consumers = Arc::new(cons_vec);
+        let tracks = Arc::new(TrackList::new(slots));
+
+        // 2) 컨트롤 ↔ 렌더 메시지 채널
+        let (render_tx, inbox) = RingBuffer::<RenderMsg>::new(RENDER_MSG_CAPACITY);
+        let (trash, render_trash) = RingBuffer::<RenderGarbage>::new(RENDER_TRASH_CAPACITY);
+        let rebuffer: Arc<RebufferSlot> = Arc::new(AtomicCell::new(None));
+        let render = RenderState {
+            tracks: render_tracks,
+            sfx: None,
+            epoch: 0,
+            inbox,
+            rebuffer: Arc::clone(&rebuffer),
+            trash,
+        };
 
         // 3) 생성
-        let params = Arc::new(Parameters::from_tracks(&tk));
+        let params = Arc::new(Parameters::new());
         let stop = Arc::new(AtomicBool::new(false));
-        let wait = Arc::new((Mutex::new(false), Condvar::new()));
-        let rt: Arc<Vec<Mutex<TrackTimeline>>> = Arc::new(
-            (0..tk.len())
-                .map(|_| {
-                    Mutex::new(TrackTimeline {
-                        clips: BTreeMap::new(),
-                        write_pos_frames: 0,
-                    })
-                })
-                .collect(),
-        );
-        let decs: Arc<Vec<Mutex<Option<DecoderState>>>> =
-            Arc::new((0..tk.len()).map(|_| Mutex::new(None)).collect());
+        let paused = Arc::new(AtomicBool::new(true)); //play 전까지는 잠든 상태
         let playing = Arc::new(Transport::new(48_000));
 
         let seek_epoch = Arc::new(AtomicU64::new(0));
 
         let seek_lock = Arc::new(Mutex::new(()));
-
-        // 4) 디코딩 스레드 포인터 클론
-        let decoding_workers: usize = 4;
-        let mut worker = Vec::with_capacity(decoding_workers + 1);
+        let edits = Arc::new(EditQueue::new());
+
+        // 4) 디코딩 워커: 트랙은 id % 워커 수 로 한 워커에만 배정
+        //    할 일이 없으면 park(), 렌더(low 워터마크)/seek/편집/종료 때만 unpark
+        let decoding_workers: usize = core_count().clamp(1, 4);
+        // 남는 코어가 있을 때만 고정 (0번은 오디오/UI 몫으로 비워둠)
+        let pin_cores = core_count() >= decoding_workers + 2;
+        let mut worker = Vec::with_capacity(decoding_workers);
         for worker_id in 0..decoding_workers {
-            let rt_c = Arc::clone(&rt);
-            let prod_c = Arc::clone(&producers);
+            let tracks_c = Arc::clone(&tracks);
+            let edits_c = Arc::clone(&edits);
             let stop_c = Arc::clone(&stop);
-            let wait_c = Arc::clone(&wait);
-            let dec_c = Arc::clone(&decs);
+            let paused_c = Arc::clone(&paused);
             let playing_c = Arc::clone(&playing);
             let params_c = Arc::clone(&params);
             worker.push(thread::spawn(move || {
                 bump_priority_worker_thread();
-                pin_to_core(
-                    1 + (worker_id
-                        % (core_affinity::get_core_ids().map(|v| v.len()).unwrap_or(1)).max(1)),
-                );
-                let mut rr = 0usize;
+                if pin_cores {
+                    pin_to_core(1 + worker_id);
+                }
 
                 loop {
                     if stop_c.load(Ordering::Acquire) {
                         break;
                     }
-
-                    let ntracks = rt_c.len(); //트랙 수
-                    if ntracks == 0 {
-                        std::thread::yield_now(); //트랙이 없으면 양보
-                        continue; //다음 루프
+                    // 전역 일시정지 게이트 (정지/seek 등): 깨울 때까지 잠듦
+                    if paused_c.load(Ordering::Acquire) {
+                        thread::park();
+                        continue;
                     }
 
-                    // 라운드로빈 시작점만 한 칸씩 밀기
-                    let start = rr;
-                    rr = rr.wrapping_add(1); //다음 시작점 계산 (오버플로우 허용)
-
-                    // ★ 이번 사이클에 모든 트랙을 한 번씩 훑는다
-                    for off in 0..ntracks {
-                        if stop_c.load(Ordering::Acquire) {
-                            break;
-                        }
-                        let track_idx = (start + off) % ntracks;
+                    // 사이클 시작이 편집 적용 지점 (fill 도중엔 타임라인이 안 바뀜)
+                    let grid = TimeGrid::new(params_c.tempo(), playing_c.sr());
+                    edits_c.try_drain(&tracks_c, &grid);
 
-                        // 1차 Prod 꽉 차면 스킵 (짧은 try)
-                        if let Ok(p) = prod_c[track_idx].lock() {
-                            if p.is_full() {
-                                continue;
-                            }
+                    let tracks = tracks_c.snapshot(); //트랙 목록 스냅샷 (추가/삭제/순서 변경 반영)
+                    for slot in tracks.iter() {
+                        if slot.id as usize % decoding_workers != worker_id {
+                            continue; //내 담당 트랙이 아님
                         }
-
-                        // 전역 일시정지 게이트 (seek 등)
-                        {
-                            let (lock, cvar) = &*wait_c; //전체 대기
-                            let mut guard = lock.lock().unwrap(); //뮤텍스 잠금
-                            guard = cvar.wait_while(guard, |waiting| *waiting).unwrap(); //대기
-                            if stop_c.load(Ordering::Relaxed) {
-                                //종료 신호
-                                return;
-                            }
-                        }
-
-                        // 로컬 포화면 짧게 대기
-                        {
-                            let (mx, cv) = &*wait_c; //전체 대기
-                            let mut g = mx.lock().unwrap(); //뮤텍스 잠금
-                            while {
-                                // 1차 Prod 꽉 찼으면 대기
-                                //while condition
-                                if let Ok(p) = prod_c[track_idx].lock()
-                                //잠깐만 잡고
-                                {
-                                    p.is_full() //꽉 찼으면
-                                } else {
-                                    true //잠금 실패하면 안전하게 대기
-                                }
-                            } && !stop_c.load(Ordering::Acquire)
-                            //종료 신호 아니면
-                            {
-                                //while inner
-                                g = cv //조건변수로 짧게 대기
-                                    .wait_timeout(g, std::time::Duration::from_millis(1)) // 1ms 대기
-                                    .unwrap() //실패시 패닉
-                                    .0; //반환값에서 guard만 취함
-                            }
+                        // 렌더가 low 밑으로 떨어졌다고 표시한 트랙만 채움
+                        if !slot.refilling.load(Ordering::Acquire) {
+                            continue;
                         }
-
-                        if stop_c.load(Ordering::Acquire) {
+                        if stop_c.load(Ordering::Acquire) || paused_c.load(Ordering::Acquire) {
                             break;
                         }
 
                         let engine_sr = playing_c.sr(); //엔진 샘플링 레이트
 
-                        let mut per_iter = CHUNK_DECODE; //한 번에 최대 생산량
-                        let mut produced_total = 0usize; //이번 트랙에서 생산한 총량
+                        // tr/dec/prod 한 번만 잡고 high 워터마크까지 생산
+                        let (Ok(mut tr), Ok(mut dc), Ok(mut pd)) = (
+                            slot.timeline.lock(),
+                            slot.voices.lock(),
+                            slot.producer.lock(),
+                        ) else {
+                            slot.refilling.store(false, Ordering::Release);
+                            continue;
+                        };
+                        let tempo = params_c.tempo(); //템포 맵 스냅샷
                         loop {
                             if stop_c.load(Ordering::Acquire) {
                                 break;
                             }
-
-                            // RB1 꽉 찼으면 다음 트랙
-                            let full = if let Ok(p) = prod_c[track_idx].lock() {
-                                p.is_full() //꽉 찼으면
-                            } else {
-                                //잠금 실패하면
-                                true
-                            };
-                            if full {
-                                //꽉 찼으면
-                                break; //다음 트랙
+                            // high까지 찼으면 다음 트랙
+                            let (buffered, cap) = ring_fill_frames(&pd);
+                            let (_, high) = watermarks(cap);
+                            if buffered >= high {
+                                break;
                             }
-
-                            // tr/dec/prod 잠깐만 잡고 최대 per_iter 만큼 생산
-                            let n = {
-                                let mut tr = match rt_c[track_idx].lock() {
-                                    //트랙 타임라인
-                                    Ok(g) => g,         //뮤텍스 잠금
-                                    Err(_) => continue, //실패시 다음 트랙
-                                };
-                                let mut dc = match dec_c[track_idx].lock() {
-                                    //디코더 상태
-                                    Ok(g) => g,         //뮤텍스 잠금
-                                    Err(_) => continue, //실패시 다음 트랙
-                                };
-                                let mut pd = match prod_c[track_idx].lock() {
-                                    //1차 링버퍼 프로듀서
-                                    Ok(g) => g,         //뮤텍스 잠금
-                                    Err(_) => continue, //실패시 다음 트랙
-                                };
-                                let tempo_ratio = {
-                                    //템포 비율
-                                    let bpm_bits = params_c.bpm.load(Ordering::Relaxed); //BPM
-                                    let bpm = f32::from_bits(bpm_bits); //f32로 변환
-                                    (bpm / BASE_BPM).clamp(0.25, 4.0) //0.25~4.0 사이로 제한
-                                };
-                                let tpos = playing_c.pos_frames(); //현재 재생 위치
-                                match fill_track_once(
-                                    &mut *tr,
-                                    &mut *dc,
-                                    &mut *pd,
-                                    per_iter,
-                                    engine_sr,
-                                    tempo_ratio,
-                                    tpos,
-                                ) {
-                                    Ok(n) => n,
-                                    Err(e) => {
-                                        eprintln!(
-                                            "[worker {worker_id}] fill_track_once error: {e}"
-                                        );
-                                        0
-                                    }
+                            let per_iter = (high - buffered).min(CHUNK_DECODE); //한 번에 최대 생산량
+                            let tpos = playing_c.playhead(); //현재 재생 위치 + 반복 구간
+                            let n = match fill_track_once(
+                                &mut tr, &mut dc, &mut pd, per_iter, engine_sr, &tempo, tpos,
+                            ) {
+                                Ok(n) => n,
+                                Err(e) => {
+                                    eprintln!("[worker {worker_id}] fill_track_once error: {e}");
+                                    0
                                 }
                             };
                             if n == 0 {
-                                // 더 만들 게 없으면 양보하고 탈출
-                                std::thread::yield_now();
+                                // 더 만들 게 없으면 탈출 (다음 low 때 렌더가 다시 깨움)
                                 break;
                             }
-
-                            produced_total += n;
-                        }
-
-                        // 트랙 단위로 아무 것도 못했으면 아주 짧게 쉼
-                        if produced_total == 0 {
-                            std::thread::park_timeout(std::time::Duration::from_micros(200));
                         }
+                        slot.refilling.store(false, Ordering::Release);
                     }
+
+                    // 다음 요청까지 잠듦 (사이에 unpark가 왔으면 바로 돌아와서 다시 훑음)
+                    thread::park();
                 }
             }));
         }
+        let worker_threads: Vec<Thread> = worker.iter().map(|h| h.thread().clone()).collect();
         // 7) Self
-        return Self {
-            track: tk,
-            producers,
-            consumers,
+        Self {
+            tracks,
 
             thread_worker: worker,
+            worker_threads,
             thread_stop: stop,
-            thread_wait: wait,
+            thread_paused: paused,
 
             real_time_params: params,
-            track_run_time: rt,
-            decod: decs,
 
             play_time_manager: playing,
             seek_epoch,
-            seek_lock: seek_lock,
+            seek_lock,
+            rebuffer,
             pending_bpm: AtomicU32::new(f32::to_bits(60.0)),
             has_pending_bpm: AtomicBool::new(false),
             pad_sample: AtomicCell::new(None),
-            sfx_state: Mutex::new(None),
+            next_track_id: AtomicU64::new(n_tracks),
+            next_clip_id: AtomicU64::new(1),
+            ring_ms: AtomicU32::new(DEFAULT_RING_MS),
+            out_layout: AtomicU32::new(SpeakerLayout::Stereo.code()),
+            meter: AtomicCell::new(TimeSignature::default()),
+            edits,
+
+            render,
+            render_tx: Mutex::new(render_tx),
+            render_trash: Mutex::new(render_trash),
             underrun_callbacks: AtomicU64::new(0),
             underrun_samples: AtomicU64::new(0),
-        };
+            host_render: AtomicBool::new(false),
+            #[cfg(feature = "cpal-output")]
+            output: None,
+        }
     }
 
     fn wake_workers(&self) {
         //워커 깨우기
-        let (lock, cvar) = &*self.thread_wait;
-        *lock.lock().unwrap() = false;
-        cvar.notify_all();
+        self.thread_paused.store(false, Ordering::Release);
+        self.notify_workers();
     }
 
     fn pause_workers(&self) {
-        //워커 대기
-        let (lock, cvar) = &*self.thread_wait;
-        *lock.lock().unwrap() = true;
-        cvar.notify_all();
+        //워커 대기 (다음 사이클부터 잠듦)
+        self.thread_paused.store(true, Ordering::Release);
+    }
+
+    // 일시정지 상태는 그대로 두고 한 사이클만 돌게 함 (편집 적용 등)
+    fn notify_workers(&self) {
+        for t in self.worker_threads.iter() {
+            t.unpark();
+        }
     }
 
     fn align_write_pos_to_transport(&self) {
-        let pos = self.play_time_manager.pos_frames();
-        for tr_mx in self.track_run_time.iter() {
-            if let Ok(mut tr) = tr_mx.lock() {
+        let pos = self.play_time_manager.stream_frames();
+        for slot in self.tracks.snapshot().iter() {
+            if let Ok(mut tr) = slot.timeline.lock() {
                 tr.write_pos_frames = pos;
             }
         }
     }
 
-    fn flush_ringbuffers(&mut self) {
-        for cons_mx in self.consumers.iter() {
-            if let Ok(mut cons) = cons_mx.lock() {
-                while cons.pop().is_ok() {}
+    // -------------------------
+    // 렌더 스레드와 통신 (락 없는 큐)
+    // -------------------------
+    fn send_render(&self, msg: RenderMsg) -> bool {
+        self.collect_render_garbage();
+        match self.render_tx.lock() {
+            Ok(mut tx) => tx.push(msg).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    fn collect_render_garbage(&self) {
+        // 렌더가 돌려보낸 트랙/컨슈머/패드 샘플은 여기(컨트롤 스레드)서 해제
+        if let Ok(mut trash) = self.render_trash.lock() {
+            while trash.pop().is_ok() {}
+        }
+    }
+
+    // 리버퍼 시작: 이 순간부터 렌더는 새 컨슈머를 받을 때까지 무음 + 재생 위치 정지
+    fn begin_rebuffer(&self) -> u64 {
+        self.seek_epoch.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    // 링버퍼를 새로 만들어 프로듀서는 바로 교체, high 워터마크까지 프리필 후 컨슈머를 렌더로 넘김
+    // (seek_lock + 워커 정지 상태에서 호출)
+    fn finish_rebuffer(&self, epoch: u64) {
+        // 워커가 멈춘 동안 밀린 편집부터 반영하고 그 타임라인으로 프리필
+        self.apply_edits();
+        self.align_write_pos_to_transport();
+        // 용량은 지금의 지연 목표/샘플레이트 기준으로 다시 잡음
+        let ring_frames = self.ring_frames();
+        let mut consumers = Vec::with_capacity(self.tracks.len());
+        for slot in self.tracks.snapshot().iter() {
+            if let Ok(mut prod) = slot.producer.lock() {
+                let (tx, rx) = RingBuffer::<f32>::new(slots(ring_frames));
+                *prod = tx;
+                consumers.push((slot.id, rx));
             }
         }
+        let _ = self.prefill_rb1_blocking();
+        // 렌더가 아직 못 가져간 이전 리버퍼는 이번 것이 대신함 (그 컨슈머들은 여기서 해제)
+        self.collect_render_garbage();
+        drop(self.rebuffer.swap(Some(Box::new(Rebuffer { epoch, consumers }))));
+    }
+
+    pub fn tempo(&self) -> Arc<TempoMap> {
+        self.real_time_params.tempo()
+    }
+
+    pub fn output_layout(&self) -> SpeakerLayout {
+        SpeakerLayout::from_code(self.out_layout.load(Ordering::Relaxed))
+            .unwrap_or(SpeakerLayout::Stereo)
+    }
+
+    pub fn set_output_layout(&self, layout: SpeakerLayout) {
+        self.out_layout.store(layout.code(), Ordering::Relaxed);
+    }
+
+    fn ring_frames(&self) -> usize {
+        ring_frames_for(
+            self.ring_ms.load(Ordering::Relaxed),
+            self.play_time_manager.sr(),
+        )
+    }
+
+    // 지연 목표 변경: 링버퍼를 새 용량으로 다시 만들고 이어서 재생
+    pub fn set_ring_latency_ms(&self, ms: u32) {
+        let _guard = self.seek_lock.lock().unwrap();
+        self.ring_ms.store(ms.min(MAX_RING_MS), Ordering::Relaxed);
+        self.pause_workers();
+        let epoch = self.begin_rebuffer();
+        self.finish_rebuffer(epoch);
+        if self.play_time_manager.in_playing() {
+            self.wake_workers();
+        }
     }
 
-    fn prefill_rb1_blocking(&self, frames: usize) -> Result<(), String> {
+    // high 워터마크까지 동기 디코드
+    fn prefill_slot_blocking(&self, slot: &TrackSlot) -> Result<usize, String> {
         let sr = self.play_time_manager.sr();
-        let tpos = self.play_time_manager.pos_frames();
-        let n = self.track.len();
-        for i in 0..n {
-            let mut tr = match self.track_run_time[i].lock() {
-                Ok(g) => g,
-                Err(_) => continue,
-            };
-            let mut dec = match self.decod[i].lock() {
-                Ok(g) => g,
-                Err(_) => continue,
-            };
-            let mut prod = match self.producers[i].lock() {
-                Ok(g) => g,
-                Err(_) => continue,
-            };
-            let tempo_ratio = {
-                let bpm_bits = self.real_time_params.bpm.load(Ordering::Relaxed);
-                let bpm = f32::from_bits(bpm_bits);
-                (bpm / BASE_BPM).clamp(0.25, 4.0)
-            };
-            let produced =
-                fill_track_once(&mut tr, &mut dec, &mut prod, frames, sr, tempo_ratio, tpos)?;
+        let tpos = self.play_time_manager.playhead();
+        let tempo = self.tempo();
+        let (Ok(mut tr), Ok(mut dec), Ok(mut prod)) = (
+            slot.timeline.lock(),
+            slot.voices.lock(),
+            slot.producer.lock(),
+        ) else {
+            return Ok(0);
+        };
+        let (buffered, cap) = ring_fill_frames(&prod);
+        let frames = watermarks(cap).1.saturating_sub(buffered);
+        slot.refilling.store(false, Ordering::Relaxed);
+        fill_track_once(&mut tr, &mut dec, &mut prod, frames, sr, &tempo, tpos)
+    }
+
+    fn prefill_rb1_blocking(&self) -> Result<(), String> {
+        for slot in self.tracks.snapshot().iter() {
+            self.prefill_slot_blocking(slot)?;
         }
         Ok(())
     }
@@ -478,32 +806,155 @@ impl Engine {
         let _guard = lock.lock().unwrap();
 
         self.pause_workers();
-
-        self.align_write_pos_to_transport();
-        self.seek_epoch.fetch_add(1, Ordering::Release);
+        let epoch = self.begin_rebuffer();
 
         if self.has_pending_bpm.swap(false, Ordering::AcqRel) {
-            let b_bits = self.pending_bpm.load(Ordering::Acquire);
-            self.real_time_params.bpm.store(b_bits, Ordering::Relaxed);
+            let bpm = f32::from_bits(self.pending_bpm.load(Ordering::Acquire));
+            let events = self.tempo().events().to_vec();
+            self.set_tempo_map(TempoMap::new(bpm, events));
         }
 
         // ★★★ 여기서 디코더 리셋/시크
+        self.reset_decoders_to_transport();
+        self.finish_rebuffer(epoch);
+        if self.play_time_manager.in_playing() {
+            self.wake_workers();
+        }
+    }
+
+    pub(crate) fn set_bpm(&mut self, bpm: f32) {
+        // 방법 A) pending 플래그 써서 rebuffer에서 적용
+        self.pending_bpm.store(bpm.to_bits(), Ordering::Relaxed);
+        self.has_pending_bpm.store(true, Ordering::Release);
+        self.rebuffer_current();
+    }
+
+    fn reset_decoders_to_transport(&self) {
         let sr = self.play_time_manager.sr();
         let tpos = self.play_time_manager.pos_frames();
-        let tempo_ratio = {
-            let bpm_bits = self.real_time_params.bpm.load(Ordering::Relaxed);
-            let bpm = f32::from_bits(bpm_bits);
-            (bpm / BASE_BPM).clamp(0.25, 4.0)
+        let tempo = self.tempo();
+        for slot in self.tracks.snapshot().iter() {
+            if let (Ok(tr), Ok(mut voices)) = (slot.timeline.lock(), slot.voices.lock()) {
+                self.reset_voices_to_tpos(&tr, &mut voices, tpos, sr, &tempo);
+            }
+        }
+    }
+
+    // JUCE 콜백이 렌더를 맡는 동안 표시 (렌더 상태는 콜백 하나만 만져야 하므로 cpal 출력과 배타)
+    pub fn set_host_render(&self, on: bool) -> bool {
+        #[cfg(feature = "cpal-output")]
+        if on && self.output.is_some() {
+            return false;
+        }
+        self.host_render.store(on, Ordering::Release);
+        true
+    }
+
+    // -------------------------
+    // 샘플레이트 변경: 재생 위치/클립 위치(프레임 단위)를 새 레이트로 환산 후 리버퍼
+    // -------------------------
+    pub fn change_sample_rate(&self, new_sr: u32) -> bool {
+        if new_sr == 0 {
+            return false;
+        }
+        let _guard = self.seek_lock.lock().unwrap();
+        let old_sr = self.play_time_manager.sr();
+        if old_sr == new_sr {
+            return true;
+        }
+
+        self.pause_workers();
+        let epoch = self.begin_rebuffer();
+        // 큐에 남은 편집은 옛 레이트 기준이므로 환산 전에 반영, 되돌리기 기록의 위치도 같이 환산
+        self.with_history(|h, _| h.rescale(old_sr, new_sr));
+
+        let pos = self.play_time_manager.pos_frames();
+        let region = self.play_time_manager.loop_region();
+        let region = LoopRegion {
+            start: rescale_frames(region.start, old_sr, new_sr),
+            end: rescale_frames(region.end, old_sr, new_sr),
         };
-        for i in 0..self.track.len() {
-            if let (Ok(tr), Ok(mut dec)) = (self.track_run_time[i].lock(), self.decod[i].lock()) {
-                let _ = self.reset_decoder_to_tpos(&*tr, &mut *dec, tpos, sr, tempo_ratio);
+        let enabled = self.play_time_manager.loop_enabled();
+        self.play_time_manager.set_loop(region, enabled);
+        self.play_time_manager
+            .seek_frames(rescale_frames(pos, old_sr, new_sr));
+        self.play_time_manager.set_sr(new_sr);
+        let tempo = self.tempo().rescaled(old_sr, new_sr); //템포 이벤트 위치도 같이
+        self.real_time_params.set_tempo(tempo);
+
+        for slot in self.tracks.snapshot().iter() {
+            let Ok(mut tr) = slot.timeline.lock() else {
+                continue;
+            };
+            let old = std::mem::take(&mut tr.clips);
+            for (_, mut clip) in old {
+                clip.rescale(old_sr, new_sr);
+                tr.clips.insert((clip.tl_start, clip.id), clip);
             }
+            tr.write_pos_frames = rescale_frames(tr.write_pos_frames, old_sr, new_sr);
+        }
+        retime_clips(&self.tracks, &self.time_grid()); //박 고정 클립은 틱에서 다시 (반올림 오차 없이)
+
+        // 디코더는 새 레이트 기준 위치로 다시 시크, 링버퍼도 새 레이트 용량으로
+        self.reset_decoders_to_transport();
+        self.finish_rebuffer(epoch);
+        if self.play_time_manager.in_playing() {
+            self.wake_workers();
+        }
+        true
+    }
+
+    // -------------------------
+    // 트랙 추가/삭제/순서 변경
+    // -------------------------
+    fn insert_track(&self, index: usize, tk: TrackConfig) -> Option<usize> {
+        let _guard = self.seek_lock.lock().unwrap();
+        if self.tracks.len() >= MAX_TRACKS {
+            return None;
+        }
+        // 현재 재생 위치부터 쓰기 시작 + 조금 미리 채워서 게시 직후 언더런 방지
+        let id = self.next_track_id.fetch_add(1, Ordering::Relaxed);
+        let (slot, consumer) =
+            TrackSlot::from_config(tk, id, self.play_time_manager.stream_frames());
+        let slot = Arc::new(slot);
+        let _ = self.prefill_slot_blocking(&slot);
+        let render_track = RenderTrack {
+            slot: Arc::clone(&slot),
+            consumer,
+        };
+        if !self.send_render(RenderMsg::AddTrack(render_track)) {
+            return None;
         }
-        self.flush_ringbuffers();
+        Some(self.tracks.update(|v| {
+            let at = index.min(v.len());
+            v.insert(at, slot);
+            at
+        }))
+    }
+
+    fn remove_track(&self, index: usize) -> bool {
+        // 워커가 잡고 있던 스냅샷은 다 쓰고 나면 Arc가 자연스럽게 해제
+        let _guard = self.seek_lock.lock().unwrap();
+        let Some(id) = self.tracks.snapshot().get(index).map(|slot| slot.id) else {
+            return false;
+        };
+        // 렌더에 못 보내면 목록도 그대로 둠 (렌더만 트랙을 들고 있는 상태 방지)
+        if !self.send_render(RenderMsg::RemoveTrack(id)) {
+            return false;
+        }
+        self.tracks.update(|v| v.retain(|slot| slot.id != id));
+        true
+    }
 
-        let _ = self.prefill_rb1_blocking(PREFILL_ON_SEEK * 2);
-        self.wake_workers();
+    fn move_track(&self, from: usize, to: usize) -> bool {
+        self.tracks.update(|v| {
+            if from >= v.len() || to >= v.len() {
+                return false;
+            }
+            let slot = v.remove(from);
+            v.insert(to, slot);
+            true
+        })
     }
 
     #[inline]
@@ -519,61 +970,59 @@ impl Engine {
         // 4) 스코프 끝나며 _guard drop
     }
 
-    fn reset_decoder_to_tpos(
+    fn reset_voices_to_tpos(
         &self,
         tr: &TrackTimeline,
-        dec: &mut Option<DecoderState>,
+        voices: &mut TrackVoices,
         tpos_frames: u64,
         out_sr: u32,
-        tempo_ratio: f32,
-    ) -> Result<(), String> {
-        let Some(d) = dec.as_mut() else {
-            return Ok(());
-        };
+        tempo: &TempoMap,
+    ) {
+        for v in voices.voices.iter_mut() {
+            let d = &mut v.dec;
 
-        // tpos가 포함된 클립 찾기
-        let active = tr
-            .clips
-            .range(..=tpos_frames)
-            .next_back()
-            .and_then(|(_, c)| {
+            // 이 보이스가 맡던 클립이 tpos에 걸쳐 있는지
+            let active = tr.clips.values().find(|c| c.id == v.clip_id).filter(|c| {
                 let end = c.tl_start.saturating_add(c.tl_len);
-                if tpos_frames < end {
-                    Some(c)
-                } else {
-                    None
-                }
+                c.tl_start <= tpos_frames && tpos_frames < end && c.file_path == d.file_path
             });
 
-        // 클립이 없으면 그냥 0으로 맞추고 종료(무음 구간은 fill에서 처리)
-        let Some(clip) = active else {
-            d.decoder.reset();
-            d.sample_buf.clear();
-            d.src_pos_samples = 0;
-            return Ok(());
-        };
+            // 아니면 비워두고 다음 fill에서 맡을 클립 위치로 시크
+            let Some(clip) = active else {
+                d.decoder.reset();
+                d.sample_buf.clear();
+                d.src_pos_samples = u64::MAX;
+                d.window = SourceWindow::default();
+                d.stretch.reset();
+                continue;
+            };
 
-        // 타임라인→소스 좌표 변환(템포 반영)
-        let rel = (tpos_frames.saturating_sub(clip.tl_start)) as f64;
-        let step = (d.src_sr as f64 / out_sr as f64) * (tempo_ratio as f64);
-        let approx_src_samples = (rel * step).floor() as u64;
+            // 타임라인→소스 좌표 변환(템포 반영, 반복 구간 안 위치, 소스 시작 오프셋부터)
+            let rel = clip.loop_rel(clip.play_rel(tpos_frames)).unwrap_or(0);
+            let done = clip.src_advanced(d.src_sr, out_sr, tempo, tpos_frames, rel);
+            let mut approx_src_samples = clip.src_start + done.floor() as u64;
+            if clip.src_end > 0 {
+                approx_src_samples = approx_src_samples.min(clip.src_end);
+            }
 
-        // 정확 시크
-        let time = Time::from(approx_src_samples as f64 / d.src_sr as f64);
-        d.format
-            .seek(
+            // 정확 시크 (패킷 경계에 멈추므로 실제 도착 위치를 기록, 실패하면 fill에서 다시 시크)
+            let time = Time::from(approx_src_samples as f64 / d.src_sr as f64);
+            let seeked = d.format.seek(
                 SeekMode::Accurate,
                 SeekTo::Time {
                     time,
                     track_id: None,
                 },
-            )
-            .map_err(|e| format!("format.seek failed: {e}"))?;
+            );
 
-        d.decoder.reset();
-        d.sample_buf.clear();
-        d.src_pos_samples = approx_src_samples;
-        Ok(())
+            d.decoder.reset();
+            d.sample_buf.clear();
+            d.window.cursor = None;
+            d.src_pos_samples = match seeked {
+                Ok(at) => at.actual_ts.min(approx_src_samples),
+                Err(_) => u64::MAX,
+            };
+        }
     }
 
     fn decode_head_1s_to_48k2ch_interleaved_arc(&self, path: &str) -> Option<Arc<[f32]>> {
@@ -631,15 +1080,12 @@ impl Engine {
         // 리샘플링 스텝(선형 보간 없이 최근접 샘플 픽업: 빠르고 클릭 없음)
         let step = src_sr as f64 / OUT_SR as f64;
 
-        let mut src_samples: Vec<[f32; 2]> = Vec::new();
-        src_samples.reserve((OUT_FRAMES as f64 * step + 4.0) as usize);
+        let mut src_samples: Vec<[f32; 2]> =
+            Vec::with_capacity((OUT_FRAMES as f64 * step + 4.0) as usize);
 
         // 소스에서 최소 1초 분량 만큼 뽑기
-        'outer: loop {
-            let pkt = match format.next_packet() {
-                Ok(p) => p,
-                Err(_) => break, // EOF
-            };
+        'outer: while let Ok(pkt) = format.next_packet() {
+            // Err = EOF
             let decoded = match decoder.decode(&pkt) {
                 Ok(x) => x,
                 Err(_) => break, // 디코드 에러 -> 포기
@@ -684,9 +1130,10 @@ impl Engine {
     }
 
     pub fn project_end_frames(&self) -> u64 {
+        self.apply_edits();
         let mut end: u64 = 0;
-        for tr_mx in self.track_run_time.iter() {
-            if let Ok(tr) = tr_mx.lock() {
+        for slot in self.tracks.snapshot().iter() {
+            if let Ok(tr) = slot.timeline.lock() {
                 for c in tr.clips.values() {
                     let e = c.tl_start.saturating_add(c.tl_len);
                     if e > end {
@@ -705,8 +1152,12 @@ impl Engine {
 
 impl Drop for Engine {
     fn drop(&mut self) {
-        self.thread_stop.store(true, Ordering::Relaxed);
-        self.wake_workers();
+        // 출력 스트림부터 멈춰야 콜백이 해제 중인 엔진을 건드리지 않음
+        #[cfg(feature = "cpal-output")]
+        self.stop_output();
+
+        self.thread_stop.store(true, Ordering::Release);
+        self.notify_workers();
 
         for h in self.thread_worker.drain(..) {
             let _ = h.join();
@@ -724,6 +1175,7 @@ pub extern "C" fn rust_audio_track_new() -> *mut TrackConfig {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_audio_track_free(tk: *mut TrackConfig) {
     if tk.is_null() {
         return;
@@ -734,6 +1186,7 @@ pub extern "C" fn rust_audio_track_free(tk: *mut TrackConfig) {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_audio_engine_new(
     track0: *mut TrackConfig,
     track1: *mut TrackConfig,
@@ -753,6 +1206,24 @@ pub extern "C" fn rust_audio_engine_new(
 }
 
 #[no_mangle]
+pub extern "C" fn rust_audio_engine_new_with_tracks(count: u32) -> *mut Engine {
+    let n = count as usize;
+    if n > MAX_TRACKS {
+        return std::ptr::null_mut();
+    }
+    match Engine::with_track_count(n) {
+        Ok(eng) => Box::into_raw(Box::new(eng)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rust_audio_engine_new_empty() -> *mut Engine {
+    rust_audio_engine_new_with_tracks(0)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_audio_engine_free(eng: *mut Engine) {
     if eng.is_null() {
         return;
@@ -763,6 +1234,7 @@ pub extern "C" fn rust_audio_engine_free(eng: *mut Engine) {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_request_load_single_sample(
     engine: *mut Engine,
     path: *const c_char,
@@ -793,6 +1265,7 @@ pub extern "C" fn rust_request_load_single_sample(
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_pad_note_on(engine: *mut Engine) -> bool {
     if engine.is_null() {
         return false;
@@ -806,25 +1279,72 @@ pub extern "C" fn rust_pad_note_on(engine: *mut Engine) -> bool {
     let sample = sample_arc.clone();
     eng.pad_sample.store(Some(sample_arc)); // 재사용 가능하게 되돌리기
 
-    if let Ok(mut st) = eng.sfx_state.lock() {
-        // 리트리거: 처음부터 다시
-        *st = Some(SfxState { sample, frame: 0 });
-        true
-    } else {
-        false
-    }
+    // 리트리거: 렌더 스레드가 처음부터 다시 재생
+    eng.send_render(RenderMsg::PadOn(sample))
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_pad_note_off(engine: *mut Engine) -> bool {
     if engine.is_null() {
         return false;
     }
     let eng = unsafe { &mut *engine };
-    if let Ok(mut st) = eng.sfx_state.lock() {
-        *st = None;
-        true
-    } else {
-        false
+    eng.send_render(RenderMsg::PadOff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 48k ↔ 44.1k: 클립/반복 구간/재생 위치/템포 이벤트가 같은 시간 위치로 옮겨가고, 돌아오면 원래 값
+    #[test]
+    fn sample_rate_change_keeps_time_positions() {
+        let mut eng = Engine::with_track_count(1).unwrap();
+        let track = eng.track_id_at(0).unwrap();
+        let id = eng.alloc_clip_id();
+        let mut clip = Clip::new(id, String::new(), 44_100, 48_000, 96_000, 0, 0);
+        clip.tl_offset = 4_800;
+        clip.loop_len = 24_000;
+        clip.fade_in.frames = 480;
+        clip.fade_out.frames = 960;
+        assert!(eng.submit_edit(EditCommand::AddClip { track, clip }));
+        eng.seek(144_000);
+        let region = LoopRegion {
+            start: 96_000,
+            end: 192_000,
+        };
+        assert!(eng.set_loop(region, true));
+        assert!(eng.edit_tempo(|events| {
+            events.push(TempoEvent {
+                frame: 480_000,
+                bpm: 120.0,
+                ramp: false,
+            });
+            true
+        }));
+
+        let state = |eng: &Engine| {
+            let clip = eng
+                .read_clip(id, |c| {
+                    let fades = (c.fade_in.frames, c.fade_out.frames);
+                    (c.tl_start, c.tl_len, c.tl_offset, c.loop_len, fades)
+                })
+                .unwrap();
+            let region = eng.play_time_manager.loop_region();
+            let tempo: Vec<u64> = eng.tempo().events().iter().map(|e| e.frame).collect();
+            let pos = eng.play_time_manager.pos_frames();
+            (clip, (region.start, region.end), tempo, pos)
+        };
+        let before = state(&eng);
+
+        assert!(eng.change_sample_rate(44_100));
+        let clip = (44_100, 88_200, 4_410, 22_050, (441, 882));
+        let after = (clip, (88_200, 176_400), vec![441_000], 132_300);
+        assert_eq!(state(&eng), after);
+        assert!(eng.play_time_manager.loop_enabled());
+
+        assert!(eng.change_sample_rate(48_000));
+        assert_eq!(state(&eng), before);
     }
 }
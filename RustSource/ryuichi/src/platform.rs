@@ -0,0 +1,52 @@
+// -------------------------
+// OS별 워커 스레드 우선순위 / 코어 고정
+// -------------------------
+
+#[cfg(windows)]
+pub fn bump_priority_worker_thread() {
+    use windows::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_ABOVE_NORMAL,
+    };
+    unsafe {
+        let h = GetCurrentThread(); //Windows API 핸들 얻기
+        let _ = SetThreadPriority(h, THREAD_PRIORITY_ABOVE_NORMAL); //우선순위 올리기
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn bump_priority_worker_thread() {
+    // 디코드 워커는 오디오 콜백보다 낮은 실시간 우선순위면 충분
+    const WORKER_FIFO_PRIORITY: libc::c_int = 10;
+    const WORKER_NICE: libc::c_int = -5;
+    unsafe {
+        let param = libc::sched_param {
+            sched_priority: WORKER_FIFO_PRIORITY,
+        };
+        if libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) != 0 {
+            // RLIMIT_RTPRIO 권한이 없으면(EPERM) 이 스레드의 nice 값만 낮춤
+            let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+            let _ = libc::setpriority(libc::PRIO_PROCESS, tid, WORKER_NICE);
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn bump_priority_worker_thread() {
+    // 지원하지 않는 OS: 기본 우선순위 유지
+}
+
+pub fn core_count() -> usize {
+    core_affinity::get_core_ids()
+        .map(|v| v.len())
+        .unwrap_or(1)
+        .max(1)
+}
+
+pub fn pin_to_core(core_id: usize) {
+    // core_affinity가 Windows/Linux를 처리, 그 외 OS는 코어 목록이 없어서 그대로 통과
+    if let Some(core) = core_affinity::get_core_ids().and_then(|v| v.get(core_id).cloned())
+    //코어 ID 유효성 검사
+    {
+        let _ = core_affinity::set_for_current(core); //현재 스레드를 해당 코어에 고정
+    }
+}
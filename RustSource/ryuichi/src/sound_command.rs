@@ -0,0 +1,802 @@
+use crate::unit::*;
+use crate::Clip;
+use crate::Engine;
+use crate::Fade;
+use crate::TimeGrid;
+use crate::TrackList;
+use crate::{ClipKey, TrackSlot};
+use crate::{Consumer, Producer, RingBuffer};
+use crate::{History, HistoryOp};
+use crate::{PitchMode, TempoMode};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+// -------------------------
+// 타임라인 편집 명령 (믹서 값은 큐를 안 타고 Engine::set_mixer로 바로)
+// 트랙은 번호가 아니라 id로 가리킴 (큐에 있는 동안 순서가 바뀌어도 같은 트랙)
+// -------------------------
+pub enum EditCommand {
+    AddClip {
+        track: u64,
+        clip: Clip,
+    },
+    MoveClip {
+        clip: ClipRef,
+        new_track: u64,
+        new_start: u64,
+    },
+    DeleteClip {
+        clip: ClipRef,
+    },
+    SplitClip {
+        clip: u64,
+        at: u64,
+        new_id: u64, //뒷조각 id
+    },
+    DuplicateClip {
+        clip: u64,
+        new_id: u64,
+        track: u64,
+        start: u64,
+    },
+    ResizeClip {
+        clip: u64,
+        tl_len: u64,
+    },
+    SetClipSource {
+        clip: u64,
+        src_start: u64,
+        src_end: u64,
+    },
+    SetClipFades {
+        clip: u64,
+        fade_in: Option<Fade>, //None = 그대로 둠
+        fade_out: Option<Fade>,
+    },
+    SetClipGain {
+        clip: u64,
+        gain_db: f32,
+        invert: bool,
+    },
+    SetClipLoop {
+        clip: u64,
+        loop_len: u64,
+        loop_count: u32,
+    },
+    SetClipReversed {
+        clip: u64,
+        reversed: bool,
+    },
+    SetClipTempoMode {
+        clip: u64,
+        mode: TempoMode,
+    },
+    SetClipPitch {
+        clip: u64,
+        cents: f32,
+        mode: PitchMode,
+    },
+    // 박 고정 켜기/끄기 (켜면 지금 위치를 가장 가까운 틱으로)
+    SetClipAnchor {
+        clip: u64,
+        anchored: bool,
+    },
+    // 되돌리기용: 같은 id 클립을 이 상태로 통째로 바꿈 (트랙은 그대로)
+    RestoreClip {
+        clip: Clip,
+    },
+    // 여러 편집을 되돌리기 한 번으로 묶음 (큐 순서대로 적용되도록 명령으로 보냄)
+    BeginGroup,
+    EndGroup,
+}
+
+// 클립 지정: id, 또는 예전 방식 (트랙, 시작 위치) — 같은 위치에 여럿이면 먼저 추가된 것
+#[derive(Clone, Copy)]
+pub enum ClipRef {
+    Id(u64),
+    At { track: u64, start: u64 },
+}
+
+// 여러 FFI 스레드 → 엔진 (프로듀서는 뮤텍스로 MPSC, 적용은 컨슈머 잡은 한 스레드만)
+pub struct EditQueue {
+    tx: Mutex<Producer<EditCommand>>,
+    rx: Mutex<Consumer<EditCommand>>,
+    history: Mutex<History>, //잠금 순서: rx → history
+    rejected: AtomicU64,     //큐에서 적용하다 거부된 명령 수 (돌려줄 호출자가 없으므로 셈)
+}
+impl EditQueue {
+    pub fn new() -> Self {
+        let (tx, rx) = RingBuffer::<EditCommand>::new(EDIT_QUEUE_CAPACITY);
+        Self {
+            tx: Mutex::new(tx),
+            rx: Mutex::new(rx),
+            history: Mutex::new(History::new()),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    pub fn push(&self, cmd: EditCommand) -> bool {
+        match self.tx.lock() {
+            Ok(mut tx) => tx.push(cmd).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    // 쌓인 명령을 들어온 순서대로 전부 적용 (컨트롤 지점: 끝날 때까지 기다림)
+    pub fn drain(&self, tracks: &TrackList, grid: &TimeGrid) -> usize {
+        match self.rx.lock() {
+            Ok(mut rx) => self.apply_all(&mut rx, tracks, grid),
+            Err(_) => 0,
+        }
+    }
+
+    // 워커용: 다른 스레드가 적용 중이면 이번엔 넘어감
+    pub fn try_drain(&self, tracks: &TrackList, grid: &TimeGrid) -> usize {
+        match self.rx.try_lock() {
+            Ok(mut rx) => self.apply_all(&mut rx, tracks, grid),
+            Err(_) => 0,
+        }
+    }
+
+    // 쌓인 명령을 먼저 적용(기록)한 뒤, 컨슈머를 잡은 채로 기록을 다룸
+    pub fn with_history<R>(
+        &self,
+        tracks: &TrackList,
+        grid: &TimeGrid,
+        f: impl FnOnce(&mut History, &TrackList) -> R,
+    ) -> Option<R> {
+        let mut rx = self.rx.lock().ok()?;
+        self.apply_all(&mut rx, tracks, grid);
+        let mut history = self.history.lock().ok()?;
+        Some(f(&mut history, tracks))
+    }
+
+    fn apply_all(
+        &self,
+        rx: &mut Consumer<EditCommand>,
+        tracks: &TrackList,
+        grid: &TimeGrid,
+    ) -> usize {
+        let Ok(mut history) = self.history.lock() else {
+            return 0;
+        };
+        let mut n = 0usize;
+        while let Ok(cmd) = rx.pop() {
+            if apply_recorded(&mut history, cmd, tracks, grid).is_err() {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+            }
+            n += 1;
+        }
+        n
+    }
+
+    // 밀린 명령 뒤에 cmd를 바로 적용 (큐를 거치지 않으므로 결과를 그대로 돌려줄 수 있음)
+    pub fn apply_now(
+        &self,
+        cmd: EditCommand,
+        tracks: &TrackList,
+        grid: &TimeGrid,
+    ) -> Result<(), String> {
+        let mut rx = self
+            .rx
+            .lock()
+            .map_err(|_| "edit queue poisoned".to_string())?;
+        self.apply_all(&mut rx, tracks, grid);
+        let mut history = self
+            .history
+            .lock()
+            .map_err(|_| "edit history poisoned".to_string())?;
+        apply_recorded(&mut history, cmd, tracks, grid)
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+}
+
+// 명령 하나 적용 + 되돌리기 기록 (묶음 표시는 기록에만 반영), 대상이 없으면 Err
+fn apply_recorded(
+    history: &mut History,
+    cmd: EditCommand,
+    tracks: &TrackList,
+    grid: &TimeGrid,
+) -> Result<(), String> {
+    match cmd {
+        EditCommand::BeginGroup => history.begin_group(),
+        EditCommand::EndGroup => history.end_group(),
+        cmd => {
+            let inverse = apply(cmd, tracks, grid).ok_or("edit target not found")?;
+            history.record(inverse.into_iter().map(HistoryOp::Edit).collect());
+        }
+    }
+    Ok(())
+}
+impl Default for EditQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 클립이 있는 트랙과 키 찾기 (id는 모든 트랙을 훑음)
+fn locate(tracks: &TrackList, clip: ClipRef) -> Option<(Arc<TrackSlot>, ClipKey)> {
+    match clip {
+        ClipRef::Id(id) => tracks.snapshot().iter().find_map(|slot| {
+            let tr = slot.timeline.lock().ok()?;
+            let key = tr.clips.values().find(|c| c.id == id)?;
+            let key = (key.tl_start, key.id);
+            Some((Arc::clone(slot), key))
+        }),
+        ClipRef::At { track, start } => {
+            let slot = tracks.find(track)?;
+            let key = {
+                let tr = slot.timeline.lock().ok()?;
+                let (key, _) = tr.clips.range((start, 0)..=(start, u64::MAX)).next()?;
+                *key
+            };
+            Some((slot, key))
+        }
+    }
+}
+
+// 명령이 가리키는 트랙/클립이 지금 타임라인에 있는지 (접수 시점 검사용)
+fn target_exists(tracks: &TrackList, cmd: &EditCommand) -> bool {
+    let clip = |id: u64| locate(tracks, ClipRef::Id(id)).is_some();
+    match cmd {
+        EditCommand::AddClip { track, .. } => tracks.find(*track).is_some(),
+        EditCommand::MoveClip {
+            clip: c, new_track, ..
+        } => locate(tracks, *c).is_some() && tracks.find(*new_track).is_some(),
+        EditCommand::DeleteClip { clip: c } => locate(tracks, *c).is_some(),
+        EditCommand::DuplicateClip {
+            clip: c, track, ..
+        } => clip(*c) && tracks.find(*track).is_some(),
+        EditCommand::SplitClip { clip: c, .. }
+        | EditCommand::ResizeClip { clip: c, .. }
+        | EditCommand::SetClipSource { clip: c, .. }
+        | EditCommand::SetClipFades { clip: c, .. }
+        | EditCommand::SetClipGain { clip: c, .. }
+        | EditCommand::SetClipLoop { clip: c, .. }
+        | EditCommand::SetClipReversed { clip: c, .. }
+        | EditCommand::SetClipTempoMode { clip: c, .. }
+        | EditCommand::SetClipPitch { clip: c, .. }
+        | EditCommand::SetClipAnchor { clip: c, .. } => clip(*c),
+        EditCommand::RestoreClip { clip: c } => clip(c.id),
+        EditCommand::BeginGroup | EditCommand::EndGroup => true,
+    }
+}
+
+impl EditCommand {
+    // 샘플레이트 변경: 명령에 담긴 타임라인 프레임 값을 새 레이트로 (되돌리기 기록용)
+    pub(crate) fn rescale(&mut self, old_sr: u32, new_sr: u32) {
+        let r = |f: &mut u64| *f = rescale_frames(*f, old_sr, new_sr);
+        match self {
+            EditCommand::AddClip { clip, .. } | EditCommand::RestoreClip { clip } => {
+                clip.rescale(old_sr, new_sr)
+            }
+            EditCommand::MoveClip {
+                clip, new_start, ..
+            } => {
+                if let ClipRef::At { start, .. } = clip {
+                    r(start);
+                }
+                r(new_start);
+            }
+            EditCommand::DeleteClip {
+                clip: ClipRef::At { start, .. },
+            } => r(start),
+            EditCommand::SplitClip { at, .. } => r(at),
+            EditCommand::DuplicateClip { start, .. } => r(start),
+            EditCommand::ResizeClip { tl_len, .. } => {
+                r(tl_len);
+                *tl_len = (*tl_len).max(1);
+            }
+            EditCommand::SetClipFades {
+                fade_in, fade_out, ..
+            } => {
+                for fade in [fade_in, fade_out].into_iter().flatten() {
+                    r(&mut fade.frames);
+                }
+            }
+            EditCommand::SetClipLoop { loop_len, .. } => r(loop_len),
+            // 소스 구간은 소스 레이트 기준, 나머지는 프레임 값이 없음
+            _ => {}
+        }
+    }
+}
+
+// id로 찾은 클립 하나만 고치는 편집 (되돌리기 = 고치기 전 클립으로 복원)
+fn edit_clip(tracks: &TrackList, id: u64, f: impl FnOnce(&mut Clip)) -> Option<Vec<EditCommand>> {
+    let (slot, key) = locate(tracks, ClipRef::Id(id))?;
+    let mut tr = slot.timeline.lock().ok()?;
+    let clip = tr.clips.get_mut(&key)?;
+    let prev = clip.clone();
+    f(clip);
+    Some(vec![EditCommand::RestoreClip { clip: prev }])
+}
+
+// 위치/길이가 바뀔 수 있는 편집: 키가 바뀌므로 빼서 고친 뒤 다시 넣음 (박 고정 클립은 틱도 갱신)
+fn edit_clip_timing(
+    tracks: &TrackList,
+    id: u64,
+    grid: &TimeGrid,
+    f: impl FnOnce(&mut Clip),
+) -> Option<Vec<EditCommand>> {
+    let (slot, key) = locate(tracks, ClipRef::Id(id))?;
+    let mut tr = slot.timeline.lock().ok()?;
+    let mut clip = tr.clips.remove(&key)?;
+    let prev = clip.clone();
+    f(&mut clip);
+    clip.sync_anchor(grid);
+    tr.clips.insert((clip.tl_start, clip.id), clip);
+    Some(vec![EditCommand::RestoreClip { clip: prev }])
+}
+
+// 적용 성공 시 되돌리는 명령들(적용할 순서대로)을 돌려줌, 실패하면 None
+pub(crate) fn apply(
+    cmd: EditCommand,
+    tracks: &TrackList,
+    grid: &TimeGrid,
+) -> Option<Vec<EditCommand>> {
+    match cmd {
+        EditCommand::AddClip { track, mut clip } => {
+            let slot = tracks.find(track)?;
+            let mut tr = slot.timeline.lock().ok()?;
+            clip.retime(grid); //되살린 클립은 지금 템포 기준 위치로
+            let id = clip.id;
+            tr.clips.insert((clip.tl_start, id), clip);
+            Some(vec![EditCommand::DeleteClip {
+                clip: ClipRef::Id(id),
+            }])
+        }
+        EditCommand::MoveClip {
+            clip,
+            new_track,
+            new_start,
+        } => {
+            let (id, old_track, old_start) = move_clip(tracks, clip, new_track, new_start, grid)?;
+            Some(vec![EditCommand::MoveClip {
+                clip: ClipRef::Id(id),
+                new_track: old_track,
+                new_start: old_start,
+            }])
+        }
+        EditCommand::DeleteClip { clip } => {
+            let (slot, key) = locate(tracks, clip)?;
+            let mut tr = slot.timeline.lock().ok()?;
+            let removed = tr.clips.remove(&key)?;
+            Some(vec![EditCommand::AddClip {
+                track: slot.id,
+                clip: removed,
+            }])
+        }
+        EditCommand::SplitClip { clip, at, new_id } => {
+            let (slot, key) = locate(tracks, ClipRef::Id(clip))?;
+            let mut tr = slot.timeline.lock().ok()?;
+            let prev = tr.clips.get(&key).cloned()?;
+            if !tr.split_clip(key, at, new_id) {
+                return None;
+            }
+            tr.sync_anchor(key, grid);
+            tr.sync_anchor((at, new_id), grid);
+            Some(vec![
+                EditCommand::DeleteClip {
+                    clip: ClipRef::Id(new_id),
+                },
+                EditCommand::RestoreClip { clip: prev },
+            ])
+        }
+        EditCommand::DuplicateClip {
+            clip,
+            new_id,
+            track,
+            start,
+        } => {
+            let (src_slot, key) = locate(tracks, ClipRef::Id(clip))?;
+            let dst_slot = tracks.find(track)?;
+            let copy = match src_slot.timeline.lock() {
+                Ok(tr) => tr.clips.get(&key).cloned(),
+                Err(_) => None,
+            };
+            let mut copy = copy?;
+            let mut dst = dst_slot.timeline.lock().ok()?;
+            copy.id = new_id;
+            copy.tl_start = start;
+            copy.sync_anchor(grid);
+            dst.clips.insert((copy.tl_start, new_id), copy);
+            Some(vec![EditCommand::DeleteClip {
+                clip: ClipRef::Id(new_id),
+            }])
+        }
+        EditCommand::ResizeClip { clip, tl_len } => {
+            let (slot, key) = locate(tracks, ClipRef::Id(clip))?;
+            let mut tr = slot.timeline.lock().ok()?;
+            let prev = tr.clips.get(&key).cloned()?;
+            if !tr.resize_clip(key, tl_len, grid) {
+                return None;
+            }
+            tr.sync_anchor(key, grid);
+            Some(vec![EditCommand::RestoreClip { clip: prev }])
+        }
+        EditCommand::SetClipSource {
+            clip,
+            src_start,
+            src_end,
+        } => edit_clip(tracks, clip, |c| {
+            c.src_start = src_start;
+            c.src_end = src_end;
+        }),
+        EditCommand::SetClipFades {
+            clip,
+            fade_in,
+            fade_out,
+        } => edit_clip(tracks, clip, |c| {
+            if let Some(fade) = fade_in {
+                c.fade_in = fade;
+            }
+            if let Some(fade) = fade_out {
+                c.fade_out = fade;
+            }
+        }),
+        EditCommand::SetClipGain {
+            clip,
+            gain_db,
+            invert,
+        } => edit_clip(tracks, clip, |c| {
+            c.gain_db = gain_db.clamp(MIN_CLIP_GAIN_DB, MAX_CLIP_GAIN_DB);
+            c.invert = invert;
+        }),
+        EditCommand::SetClipLoop {
+            clip,
+            loop_len,
+            loop_count,
+        } => edit_clip_timing(tracks, clip, grid, |c| {
+            c.loop_len = loop_len;
+            c.loop_count = loop_count;
+        }),
+        EditCommand::SetClipReversed { clip, reversed } => {
+            edit_clip(tracks, clip, |c| c.reversed = reversed)
+        }
+        EditCommand::SetClipTempoMode { clip, mode } => {
+            edit_clip(tracks, clip, |c| c.tempo_mode = mode)
+        }
+        EditCommand::SetClipPitch { clip, cents, mode } => edit_clip(tracks, clip, |c| {
+            c.pitch_cents = cents.clamp(-MAX_CLIP_PITCH_CENTS, MAX_CLIP_PITCH_CENTS);
+            c.pitch_mode = mode;
+        }),
+        EditCommand::SetClipAnchor { clip, anchored } => {
+            edit_clip_timing(tracks, clip, grid, |c| {
+                c.anchor = anchored.then(|| c.anchor.unwrap_or_default());
+            })
+        }
+        EditCommand::RestoreClip { mut clip } => {
+            let (slot, key) = locate(tracks, ClipRef::Id(clip.id))?;
+            let mut tr = slot.timeline.lock().ok()?;
+            let prev = tr.clips.remove(&key)?;
+            clip.retime(grid);
+            tr.clips.insert((clip.tl_start, clip.id), clip);
+            Some(vec![EditCommand::RestoreClip { clip: prev }])
+        }
+        // 묶음 표시는 큐에서 꺼낼 때 처리, 기록 안에는 들어가지 않음
+        EditCommand::BeginGroup | EditCommand::EndGroup => Some(Vec::new()),
+    }
+}
+
+// 성공하면 (클립 id, 원래 트랙 id, 원래 시작 위치)
+fn move_clip(
+    tracks: &TrackList,
+    clip: ClipRef,
+    new_id: u64,
+    new_start: u64,
+    grid: &TimeGrid,
+) -> Option<(u64, u64, u64)> {
+    let (old_slot, old_key) = locate(tracks, clip)?;
+    let new_slot = tracks.find(new_id)?;
+    let old_id = old_slot.id;
+
+    // 동일 트랙: 단일 락으로 원자적 처리
+    if old_id == new_id {
+        let mut tr = old_slot.timeline.lock().ok()?;
+        let mut clip = tr.clips.remove(&old_key)?; // 이동할 클립이 없으면 실패
+        clip.tl_start = new_start;
+        clip.sync_anchor(grid);
+        let clip_id = clip.id;
+        tr.clips.insert((clip.tl_start, clip_id), clip);
+        return Some((clip_id, old_id, old_key.0));
+    }
+
+    // 서로 다른 트랙: 락 순서 고정(id 작은 쪽 먼저)로 데드락 방지
+    let (first, second) = if old_id < new_id {
+        (&old_slot, &new_slot)
+    } else {
+        (&new_slot, &old_slot)
+    };
+    let mut t_first = first.timeline.lock().ok()?;
+    let mut t_second = second.timeline.lock().ok()?;
+    let (src, dst) = if old_id < new_id {
+        (&mut *t_first, &mut *t_second)
+    } else {
+        (&mut *t_second, &mut *t_first)
+    };
+
+    let mut clip = src.clips.remove(&old_key)?;
+    clip.tl_start = new_start;
+    clip.sync_anchor(grid);
+    let clip_id = clip.id;
+    dst.clips.insert((clip.tl_start, clip_id), clip);
+    Some((clip_id, old_id, old_key.0))
+}
+
+impl Engine {
+    // 정지 중엔 바로 적용하고 그 결과를, 재생 중엔 대상이 있는지 확인 후 큐에 접수한 결과를 돌려줌
+    // (재생 중 실제 적용은 워커 사이클의 drain 지점에서 순서대로)
+    pub fn submit_edit(&self, cmd: EditCommand) -> bool {
+        if !self.play_time_manager.in_playing() {
+            return self
+                .edits
+                .apply_now(cmd, &self.tracks, &self.time_grid())
+                .is_ok();
+        }
+        // 대상이 아직 큐에 있는 AddClip일 수 있으니 못 찾으면 밀린 편집을 적용하고 한 번 더
+        if !target_exists(&self.tracks, &cmd) {
+            self.apply_edits();
+            if !target_exists(&self.tracks, &cmd) {
+                return false;
+            }
+        }
+        if !self.edits.push(cmd) {
+            return false;
+        }
+        self.notify_workers();
+        true
+    }
+
+    pub fn apply_edits(&self) -> usize {
+        self.edits.drain(&self.tracks, &self.time_grid())
+    }
+
+    // 재생 중 큐로 접수된 뒤 적용 시점에 거부된 편집 수
+    pub fn rejected_edits(&self) -> u64 {
+        self.edits.rejected()
+    }
+
+    // 트랙은 그대로 두고 클립만 전부 지움 (묶음으로 보내서 되돌리기 한 번에 돌아옴)
+    pub fn clear_track_clips(&self, track: u64) -> bool {
+        self.apply_edits(); //큐에 남은 AddClip도 지우도록 먼저 반영
+        let Some(slot) = self.tracks.find(track) else {
+            return false;
+        };
+        let ids: Vec<u64> = match slot.timeline.lock() {
+            Ok(tr) => tr.clips.values().map(|c| c.id).collect(),
+            Err(_) => return false,
+        };
+        if !self.submit_edit(EditCommand::BeginGroup) {
+            return false;
+        }
+        let mut ok = true;
+        for id in ids {
+            ok &= self.submit_edit(EditCommand::DeleteClip {
+                clip: ClipRef::Id(id),
+            });
+        }
+        self.submit_edit(EditCommand::EndGroup) && ok
+    }
+
+    // 적용된 타임라인에서 클립 하나 읽기 (큐에 남은 편집은 아직 반영 전)
+    pub fn read_clip<R>(&self, id: u64, f: impl FnOnce(&Clip) -> R) -> Option<R> {
+        let (slot, key) = locate(&self.tracks, ClipRef::Id(id))?;
+        let tr = slot.timeline.lock().ok()?;
+        tr.clips.get(&key).map(f)
+    }
+
+    // 클립 id 발급 (큐에 넣기 전에 정해서 FFI가 바로 돌려줌)
+    pub fn alloc_clip_id(&self) -> u64 {
+        self.next_clip_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // FFI 트랙 번호 → 트랙 id (접수 시점의 순서 기준)
+    pub fn track_id_at(&self, number: i32) -> Option<u64> {
+        let idx = usize::try_from(number).ok()?;
+        self.tracks.get(idx).map(|slot| slot.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MixerParam;
+    use std::ffi::CString;
+
+    fn add_clip(eng: &mut Engine, number: i32, start: u64) -> u64 {
+        let path = CString::new("missing.wav").unwrap();
+        crate::rust_sound_add_clip(eng, number, path.as_ptr(), start, 4800, 48000, 0, 0)
+    }
+
+    #[test]
+    fn clear_track_keeps_track_and_undoes_in_one_step() {
+        let mut eng = Engine::with_track_count(2).unwrap();
+        let track = eng.track_id_at(0).unwrap();
+        let ids = [add_clip(&mut eng, 0, 0), add_clip(&mut eng, 0, 9600)];
+        let other = add_clip(&mut eng, 1, 0);
+        assert!(eng.set_mixer(track, MixerParam::Volume(0.3)));
+
+        assert!(eng.clear_track_clips(track));
+        assert_eq!(eng.track_id_at(0), Some(track));
+        assert!(ids.iter().all(|&id| eng.read_clip(id, |_| ()).is_none()));
+        assert!(eng.read_clip(other, |_| ()).is_some());
+        let slot = eng.tracks.find(track).unwrap();
+        let volume = f32::from_bits(slot.params.volume.load(Ordering::Relaxed));
+        assert_eq!(volume, 0.3);
+
+        assert!(eng.undo());
+        assert!(ids.iter().all(|&id| eng.read_clip(id, |_| ()).is_some()));
+    }
+
+    fn new_clip(eng: &Engine, start: u64) -> Clip {
+        Clip::new(
+            eng.alloc_clip_id(),
+            String::new(),
+            48_000,
+            start,
+            4800,
+            0,
+            0,
+        )
+    }
+
+    // (트랙 id, 시작 위치)
+    fn place(eng: &Engine, id: u64) -> Option<(u64, u64)> {
+        locate(&eng.tracks, ClipRef::Id(id)).map(|(slot, key)| (slot.id, key.0))
+    }
+
+    #[test]
+    fn queued_edits_wait_for_drain() {
+        let eng = Engine::with_track_count(1).unwrap();
+        let grid = eng.time_grid();
+        let track = eng.track_id_at(0).unwrap();
+        let a = new_clip(&eng, 0);
+        let a_id = a.id;
+        assert!(eng.edits.push(EditCommand::AddClip { track, clip: a }));
+        assert_eq!(place(&eng, a_id), None);
+
+        // 바로 적용하는 명령은 밀린 명령 뒤에 순서대로
+        let b = new_clip(&eng, 9_600);
+        let b_id = b.id;
+        let add = EditCommand::AddClip { track, clip: b };
+        assert!(eng.edits.apply_now(add, &eng.tracks, &grid).is_ok());
+        assert_eq!(place(&eng, a_id), Some((track, 0)));
+        assert_eq!(place(&eng, b_id), Some((track, 9_600)));
+
+        assert!(eng.edits.push(EditCommand::DeleteClip {
+            clip: ClipRef::Id(a_id),
+        }));
+        assert_eq!(place(&eng, a_id), Some((track, 0)));
+        assert_eq!(eng.apply_edits(), 1);
+        assert_eq!(place(&eng, a_id), None);
+    }
+
+    #[test]
+    fn missing_target_is_rejected() {
+        let mut eng = Engine::with_track_count(1).unwrap();
+        let grid = eng.time_grid();
+        let track = eng.track_id_at(0).unwrap();
+        let id = add_clip(&mut eng, 0, 1_000);
+        assert!(eng.undo());
+        assert!(!eng.can_undo());
+
+        let gone = EditCommand::DeleteClip {
+            clip: ClipRef::Id(id),
+        };
+        assert!(eng.edits.apply_now(gone, &eng.tracks, &grid).is_err());
+        let wrong_start = EditCommand::MoveClip {
+            clip: ClipRef::At { track, start: 999 },
+            new_track: track,
+            new_start: 0,
+        };
+        assert!(eng
+            .edits
+            .apply_now(wrong_start, &eng.tracks, &grid)
+            .is_err());
+        assert!(!eng.submit_edit(EditCommand::ResizeClip {
+            clip: id,
+            tl_len: 10
+        }));
+
+        // 큐에서 거부되면 호출자가 이미 돌아갔으므로 개수로 남고, 기록은 안 생김
+        assert!(eng.edits.push(EditCommand::ResizeClip {
+            clip: id,
+            tl_len: 10
+        }));
+        assert_eq!(eng.apply_edits(), 1);
+        assert_eq!(eng.rejected_edits(), 1);
+        assert!(!eng.can_undo());
+    }
+
+    #[test]
+    fn inverse_restores_previous_state() {
+        let eng = Engine::with_track_count(2).unwrap();
+        let grid = eng.time_grid();
+        let (t0, t1) = (eng.track_id_at(0).unwrap(), eng.track_id_at(1).unwrap());
+        let clip = new_clip(&eng, 1_000);
+        let id = clip.id;
+        let undo_all = |inverse: Vec<EditCommand>| {
+            for cmd in inverse {
+                assert!(apply(cmd, &eng.tracks, &grid).is_some());
+            }
+        };
+
+        let inverse = apply(EditCommand::AddClip { track: t0, clip }, &eng.tracks, &grid).unwrap();
+        let [EditCommand::DeleteClip {
+            clip: ClipRef::Id(deleted),
+        }] = inverse.as_slice()
+        else {
+            panic!("add should undo as one delete");
+        };
+        assert_eq!(*deleted, id);
+
+        let inverse = apply(
+            EditCommand::MoveClip {
+                clip: ClipRef::Id(id),
+                new_track: t1,
+                new_start: 5_000,
+            },
+            &eng.tracks,
+            &grid,
+        )
+        .unwrap();
+        assert_eq!(place(&eng, id), Some((t1, 5_000)));
+        undo_all(inverse);
+        assert_eq!(place(&eng, id), Some((t0, 1_000)));
+
+        // 지우기를 되돌리면 같은 id로 같은 자리에 되살아남
+        let delete = EditCommand::DeleteClip {
+            clip: ClipRef::Id(id),
+        };
+        let inverse = apply(delete, &eng.tracks, &grid).unwrap();
+        assert_eq!(place(&eng, id), None);
+        undo_all(inverse);
+        assert_eq!(place(&eng, id), Some((t0, 1_000)));
+    }
+
+    // 두 스레드가 서로 반대 방향으로 트랙을 넘나들어도 락 순서가 고정이라 멈추지 않음
+    #[test]
+    fn cross_track_moves_do_not_deadlock() {
+        let eng = Engine::with_track_count(2).unwrap();
+        let (t0, t1) = (eng.track_id_at(0).unwrap(), eng.track_id_at(1).unwrap());
+        let grid = eng.time_grid();
+        let a = new_clip(&eng, 0);
+        let b = new_clip(&eng, 0);
+        let (a_id, b_id) = (a.id, b.id);
+        assert!(apply(
+            EditCommand::AddClip { track: t0, clip: a },
+            &eng.tracks,
+            &grid
+        )
+        .is_some());
+        assert!(apply(
+            EditCommand::AddClip { track: t1, clip: b },
+            &eng.tracks,
+            &grid
+        )
+        .is_some());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        for (id, from, to) in [(a_id, t0, t1), (b_id, t1, t0)] {
+            let tracks = Arc::clone(&eng.tracks);
+            let grid = TimeGrid::new(eng.tempo(), 48_000);
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                for i in 0..2_000u64 {
+                    let dest = if i % 2 == 0 { to } else { from };
+                    assert!(move_clip(&tracks, ClipRef::Id(id), dest, i, &grid).is_some());
+                }
+                let _ = tx.send(());
+            });
+        }
+        for _ in 0..2 {
+            let done = rx.recv_timeout(std::time::Duration::from_secs(30));
+            assert!(done.is_ok(), "cross-track moves stalled");
+        }
+        assert_eq!(place(&eng, a_id), Some((t0, 1_999)));
+        assert_eq!(place(&eng, b_id), Some((t1, 1_999)));
+    }
+}
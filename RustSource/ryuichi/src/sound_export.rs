@@ -0,0 +1,308 @@
+use crate::track_taps;
+use crate::unit::*;
+use crate::Engine;
+use crate::SpeakerLayout;
+use crate::TempoMap;
+use crate::TrackSlot;
+use crate::TrackTimeline;
+use crate::TrackVoices;
+use crate::{fill_track_once, Consumer, Producer, RingBuffer};
+use crate::{LoopRegion, Playhead};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+// -------------------------
+// 오프라인 렌더용 트랙 상태 (라이브 디코더/링버퍼와 완전히 분리)
+// -------------------------
+struct OfflineTrack {
+    slot: Arc<TrackSlot>,
+    timeline: TrackTimeline,
+    voices: TrackVoices,
+    producer: Producer<f32>,
+    consumer: Consumer<f32>,
+}
+
+// -------------------------
+// 오프라인 렌더 진행 상태: next_block 한 번에 블록 하나를 워커 없이 동기 디코드
+// (같은 타임라인이면 실행할 때마다 같은 샘플이 나옴, 엔진과 따로 놀아서 FFI로 넘겨 블록씩 가져가도 됨)
+// -------------------------
+pub struct OfflineRender {
+    tracks: Vec<OfflineTrack>,
+    tempo: Arc<TempoMap>,
+    sr: u32,
+    layout: SpeakerLayout,
+    pos: u64,
+    end: u64,
+    out: Vec<f32>,
+}
+
+impl OfflineRender {
+    // 다음 블록 (최대 max_frames, CHUNK_OFFLINE 넘으면 잘림), 끝까지 다 냈으면 None
+    // 블록은 layout 채널 수만큼 인터리브드, 라이브 렌더와 같은 탭으로 믹스
+    pub fn next_block(&mut self, max_frames: usize) -> Result<Option<&[f32]>, String> {
+        if self.pos >= self.end || max_frames == 0 {
+            return Ok(None);
+        }
+        let frames = ((self.end - self.pos) as usize)
+            .min(max_frames)
+            .min(CHUNK_OFFLINE);
+        let channels = self.layout.channels();
+        let block = &mut self.out[..frames * channels];
+        block.fill(0.0);
+
+        for t in self.tracks.iter_mut() {
+            // 블록 하나를 다 채울 때까지 동기 디코드
+            let mut produced = 0usize;
+            while produced < frames {
+                let n = fill_track_once(
+                    &mut t.timeline,
+                    &mut t.voices,
+                    &mut t.producer,
+                    frames - produced,
+                    self.sr,
+                    &self.tempo,
+                    Playhead {
+                        stream: self.pos,
+                        looped: LoopRegion::NONE,
+                    },
+                )?;
+                if n == 0 {
+                    break;
+                }
+                produced += n;
+            }
+
+            let taps = track_taps(&t.slot.params, Some(self.layout), channels);
+            for f in 0..frames {
+                let l = t.consumer.pop().unwrap_or(0.0);
+                let r = t.consumer.pop().unwrap_or(0.0);
+                if let Some(taps) = &taps {
+                    for &(ch, gl, gr) in taps.as_slice() {
+                        block[f * channels + ch] += l * gl + r * gr;
+                    }
+                }
+            }
+        }
+
+        self.pos += frames as u64;
+        Ok(Some(&self.out[..frames * channels]))
+    }
+}
+
+impl Engine {
+    // start..end 구간 오프라인 렌더 준비 (접수된 편집까지 포함, 타임라인은 지금 것을 복사해 둠)
+    pub fn offline_render(&self, start: u64, end: u64, layout: SpeakerLayout) -> OfflineRender {
+        self.apply_edits();
+        let mut tracks: Vec<OfflineTrack> = Vec::new();
+        for slot in self.tracks.snapshot().iter() {
+            let clips = match slot.timeline.lock() {
+                Ok(tr) => tr.clips.clone(),
+                Err(_) => continue,
+            };
+            let (producer, consumer) = RingBuffer::<f32>::new(slots(CHUNK_OFFLINE));
+            tracks.push(OfflineTrack {
+                slot: Arc::clone(slot),
+                timeline: TrackTimeline {
+                    clips,
+                    write_pos_frames: start,
+                },
+                voices: TrackVoices::new(),
+                producer,
+                consumer,
+            });
+        }
+        OfflineRender {
+            tracks,
+            tempo: self.tempo(),
+            sr: self.play_time_manager.sr(),
+            layout,
+            pos: start,
+            end: end.max(start),
+            out: vec![0.0; CHUNK_OFFLINE * layout.channels()],
+        }
+    }
+
+    // start..end 구간을 블록 단위로 on_block에 넘김
+    pub fn render_offline(
+        &self,
+        start: u64,
+        end: u64,
+        layout: SpeakerLayout,
+        mut on_block: impl FnMut(&[f32]) -> Result<(), String>,
+    ) -> Result<u64, String> {
+        if end <= start {
+            return Ok(0);
+        }
+        let mut render = self.offline_render(start, end, layout);
+        while let Some(block) = render.next_block(CHUNK_OFFLINE)? {
+            on_block(block)?;
+        }
+        Ok(end - start)
+    }
+
+    pub fn render_offline_to_wav(
+        &self,
+        path: &str,
+        start: u64,
+        end: u64,
+        bit_depth: u16,
+    ) -> Result<u64, String> {
+        let sample_format = match bit_depth {
+            16 | 24 => hound::SampleFormat::Int,
+            32 => hound::SampleFormat::Float,
+            _ => return Err(format!("unsupported bit depth: {bit_depth}")),
+        };
+        let layout = self.output_layout(); // 지금 출력 레이아웃 그대로 (5.1이면 6채널 파일)
+        let spec = hound::WavSpec {
+            channels: layout.channels() as u16,
+            sample_rate: self.play_time_manager.sr(),
+            bits_per_sample: bit_depth,
+            sample_format,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).map_err(|e| e.to_string())?;
+
+        let int_scale = ((1i64 << (bit_depth - 1)) - 1) as f32; //16bit: 32767, 24bit: 8388607
+        let written = self.render_offline(start, end, layout, |block| {
+            for &s in block {
+                let s = if s.is_finite() {
+                    s.clamp(-1.0, 1.0)
+                } else {
+                    0.0
+                };
+                let res = match sample_format {
+                    hound::SampleFormat::Float => writer.write_sample(s),
+                    hound::SampleFormat::Int => writer.write_sample((s * int_scale).round() as i32),
+                };
+                res.map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        })?;
+        writer.finalize().map_err(|e| e.to_string())?;
+        Ok(written)
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_render_offline_to_wav(
+    engine: *mut Engine,
+    path: *const c_char,
+    start: u64,
+    end: u64,
+    bit_depth: u32,
+) -> bool {
+    if engine.is_null() || path.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    let cstr = unsafe { CStr::from_ptr(path) };
+    let path_str = match cstr.to_str() {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    let Ok(bit_depth) = u16::try_from(bit_depth) else {
+        return false;
+    };
+    // end == 0 이면 프로젝트 끝까지
+    let end = if end == 0 {
+        eng.project_end_frames()
+    } else {
+        end
+    };
+    match eng.render_offline_to_wav(path_str, start, end, bit_depth) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("[rust_render_offline_to_wav] {e}");
+            false
+        }
+    }
+}
+
+// 블록씩 가져가는 오프라인 렌더 (C++ 쪽 플러그인 체인을 거쳐 파일로 쓰는 내보내기용)
+// layout: 0 = 스테레오, 1 = 쿼드, 2 = 5.1 / end == 0 이면 프로젝트 끝까지
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_offline_render_begin(
+    engine: *mut Engine,
+    start: u64,
+    end: u64,
+    layout: u32,
+) -> *mut OfflineRender {
+    if engine.is_null() {
+        return std::ptr::null_mut();
+    }
+    let eng = unsafe { &*engine };
+    let Some(layout) = SpeakerLayout::from_code(layout) else {
+        return std::ptr::null_mut();
+    };
+    let end = if end == 0 {
+        eng.project_end_frames()
+    } else {
+        end
+    };
+    Box::into_raw(Box::new(eng.offline_render(start, end, layout)))
+}
+
+// out에 최대 frames개 (layout 채널 인터리브드)를 채우고 채운 프레임 수 반환 (0 = 끝 또는 에러)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_offline_render_next(
+    render: *mut OfflineRender,
+    out: *mut f32,
+    frames: usize,
+) -> usize {
+    if render.is_null() || out.is_null() || frames == 0 {
+        return 0;
+    }
+    let render = unsafe { &mut *render };
+    let channels = render.layout.channels();
+    match render.next_block(frames) {
+        Ok(Some(block)) => {
+            let out = unsafe { std::slice::from_raw_parts_mut(out, frames * channels) };
+            out[..block.len()].copy_from_slice(block);
+            block.len() / channels
+        }
+        Ok(None) => 0,
+        Err(e) => {
+            eprintln!("[rust_offline_render_next] {e}");
+            0
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_offline_render_free(render: *mut OfflineRender) {
+    if render.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(render));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_cover_range() {
+        let eng = Engine::with_track_count(2).unwrap();
+        let mut render =
+            eng.offline_render(100, 100 + CHUNK_OFFLINE as u64 + 50, SpeakerLayout::Quad);
+        let mut sizes = Vec::new();
+        while let Some(block) = render.next_block(CHUNK_OFFLINE * 2).unwrap() {
+            assert_eq!(block.len() % 4, 0);
+            assert!(block.iter().all(|&s| s == 0.0)); // 클립이 없으면 무음
+            sizes.push(block.len() / 4);
+        }
+        assert_eq!(sizes, vec![CHUNK_OFFLINE, 50]);
+        assert!(render.next_block(10).unwrap().is_none());
+        assert!(eng
+            .offline_render(10, 5, SpeakerLayout::Stereo)
+            .next_block(10)
+            .unwrap()
+            .is_none());
+    }
+}
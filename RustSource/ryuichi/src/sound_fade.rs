@@ -0,0 +1,249 @@
+use crate::unit::db_to_gain;
+use std::f32::consts::FRAC_PI_2;
+
+// -------------------------
+// 클립 페이드 곡선
+// -------------------------
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FadeCurve {
+    #[default]
+    Linear,
+    EqualPower,  // sin/cos, 크로스페이드 시 에너지 유지
+    Exponential, // dB 기준 직선 (-60dB → 0dB)
+}
+
+const EXP_FLOOR_DB: f32 = -60.0;
+
+impl FadeCurve {
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(Self::Linear),
+            1 => Some(Self::EqualPower),
+            2 => Some(Self::Exponential),
+            _ => None,
+        }
+    }
+    pub fn code(self) -> u32 {
+        match self {
+            Self::Linear => 0,
+            Self::EqualPower => 1,
+            Self::Exponential => 2,
+        }
+    }
+    // t: 0(무음) ~ 1(원래 크기)
+    pub fn gain(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EqualPower => (t * FRAC_PI_2).sin(),
+            Self::Exponential => {
+                // 바닥(-60dB)을 0으로 끌어내려 양 끝이 정확히 0/1
+                let floor = 10f32.powf(EXP_FLOOR_DB / 20.0);
+                let g = 10f32.powf(EXP_FLOOR_DB * (1.0 - t) / 20.0);
+                ((g - floor) / (1.0 - floor)).max(0.0)
+            }
+        }
+    }
+}
+
+// 페이드 하나: 길이(엔진 샘플레이트 기준 타임라인 프레임, 0 = 없음) + 곡선
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Fade {
+    pub frames: u64,
+    pub curve: FadeCurve,
+}
+
+// 클립 안에서의 페이드 계산기 (pos: 클립 시작부터의 타임라인 프레임, 프레임마다 1씩 진행)
+#[derive(Clone, Copy)]
+pub struct ClipEnvelope {
+    fade_in: Fade,
+    fade_out: Fade,
+    len: u64,
+    pos: u64,
+    gain: f32, //클립 게인 (극성 포함, 페이드와 곱함)
+}
+impl ClipEnvelope {
+    pub fn new(fade_in: Fade, fade_out: Fade, len: u64, pos: u64) -> Self {
+        // 둘이 겹칠 만큼 길면 클립 길이 안으로 줄임 (앞쪽 페이드 우선)
+        let in_frames = fade_in.frames.min(len);
+        let out_frames = fade_out.frames.min(len - in_frames);
+        Self {
+            fade_in: Fade {
+                frames: in_frames,
+                curve: fade_in.curve,
+            },
+            fade_out: Fade {
+                frames: out_frames,
+                curve: fade_out.curve,
+            },
+            len,
+            pos,
+            gain: 1.0,
+        }
+    }
+
+    pub fn with_gain(mut self, gain_db: f32, invert: bool) -> Self {
+        let g = db_to_gain(gain_db);
+        self.gain = if invert { -g } else { g };
+        self
+    }
+
+    #[inline]
+    pub fn is_flat(&self) -> bool {
+        self.fade_in.frames == 0 && self.fade_out.frames == 0
+    }
+
+    #[inline]
+    pub fn gain_at(&self, rel: u64) -> f32 {
+        let mut g = 1.0f32;
+        if rel < self.fade_in.frames {
+            g *= self
+                .fade_in
+                .curve
+                .gain(rel as f32 / self.fade_in.frames as f32);
+        }
+        let left = self.len.saturating_sub(rel); // 클립 끝까지 남은 프레임
+        if left <= self.fade_out.frames && self.fade_out.frames > 0 {
+            // 마지막 프레임이 0에 닿도록 (left-1)/frames
+            g *= self
+                .fade_out
+                .curve
+                .gain(left.saturating_sub(1) as f32 / self.fade_out.frames as f32);
+        }
+        g
+    }
+
+    // 현재 프레임 게인을 돌려주고 한 프레임 진행
+    #[inline]
+    pub fn next_gain(&mut self) -> f32 {
+        let g = if self.is_flat() {
+            self.gain
+        } else {
+            self.gain * self.gain_at(self.pos)
+        };
+        self.pos += 1;
+        g
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clip_envelope, Clip, ClipKey};
+    use std::collections::BTreeMap;
+
+    const CURVES: [FadeCurve; 3] = [
+        FadeCurve::Linear,
+        FadeCurve::EqualPower,
+        FadeCurve::Exponential,
+    ];
+
+    fn clip(id: u64, tl_start: u64, tl_len: u64) -> Clip {
+        Clip::new(id, String::new(), 48_000, tl_start, tl_len, 0, 0)
+    }
+
+    fn timeline(clips: &[Clip]) -> BTreeMap<ClipKey, Clip> {
+        clips
+            .iter()
+            .map(|c| ((c.tl_start, c.id), c.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn curve_endpoints() {
+        for curve in CURVES {
+            assert_eq!(curve.gain(0.0), 0.0, "{curve:?}");
+            assert!((curve.gain(1.0) - 1.0).abs() < 1e-6, "{curve:?}");
+            // 범위 밖은 끝값
+            assert_eq!(curve.gain(-0.5), curve.gain(0.0));
+            assert_eq!(curve.gain(1.5), curve.gain(1.0));
+            let mut last = 0.0;
+            for i in 1..=100 {
+                let g = curve.gain(i as f32 / 100.0);
+                assert!(g >= last, "{curve:?} {i}");
+                last = g;
+            }
+        }
+        assert_eq!(FadeCurve::Linear.gain(0.25), 0.25);
+    }
+
+    #[test]
+    fn equal_power_keeps_energy() {
+        for i in 0..=100 {
+            let t = i as f32 / 100.0;
+            let a = FadeCurve::EqualPower.gain(t);
+            let b = FadeCurve::EqualPower.gain(1.0 - t);
+            assert!((a * a + b * b - 1.0).abs() < 1e-5, "{t}");
+        }
+    }
+
+    #[test]
+    fn envelope_fades() {
+        let lin = |frames| Fade {
+            frames,
+            curve: FadeCurve::Linear,
+        };
+        let env = ClipEnvelope::new(lin(100), lin(100), 1_000, 0);
+        assert_eq!(env.gain_at(0), 0.0);
+        assert_eq!(env.gain_at(50), 0.5);
+        assert_eq!(env.gain_at(100), 1.0);
+        assert_eq!(env.gain_at(900), 0.99);
+        assert_eq!(env.gain_at(999), 0.0); // 마지막 프레임은 0
+
+        // 둘이 겹칠 만큼 길면 앞쪽 페이드 우선으로 클립 안에 맞춤
+        let env = ClipEnvelope::new(lin(800), lin(800), 1_000, 0);
+        assert_eq!(env.gain_at(400), 0.5);
+        // 페이드 아웃은 남은 200 frames, 페이드 인이 끝나는 곳에서 바로 시작
+        assert_eq!(env.gain_at(800), 0.995);
+        assert_eq!(env.gain_at(899), 0.5);
+        assert_eq!(env.gain_at(999), 0.0);
+
+        // next_gain은 pos부터 한 프레임씩, 게인/극성 포함
+        let mut env = ClipEnvelope::new(lin(100), Fade::default(), 1_000, 50).with_gain(0.0, true);
+        assert_eq!(env.next_gain(), -0.5);
+        assert_eq!(env.next_gain(), -0.51);
+        let mut flat =
+            ClipEnvelope::new(Fade::default(), Fade::default(), 10, 0).with_gain(0.0, false);
+        assert!(flat.is_flat());
+        assert_eq!(flat.next_gain(), 1.0);
+    }
+
+    #[test]
+    fn overlapping_clips_crossfade_equal_power() {
+        let a = clip(1, 0, 1_000);
+        let b = clip(2, 600, 1_000);
+        let clips = timeline(&[a.clone(), b.clone()]);
+        for pos in 600..1_000 {
+            let ga = clip_envelope(&clips, &a, pos).next_gain();
+            let gb = clip_envelope(&clips, &b, pos).next_gain();
+            assert!((ga * ga + gb * gb - 1.0).abs() < 1e-2, "{pos}");
+        }
+        // 겹치기 전/후는 그대로
+        assert_eq!(clip_envelope(&clips, &a, 599).next_gain(), 1.0);
+        assert_eq!(clip_envelope(&clips, &b, 1_000).next_gain(), 1.0);
+    }
+
+    #[test]
+    fn envelope_prefers_longer_fade() {
+        let mut a = clip(1, 0, 1_000);
+        let b = clip(2, 900, 1_000);
+        // 사용자 페이드가 겹친 길이보다 길면 그걸 씀
+        a.fade_out = Fade {
+            frames: 500,
+            curve: FadeCurve::Linear,
+        };
+        let clips = timeline(&[a.clone(), b.clone()]);
+        assert_eq!(clip_envelope(&clips, &a, 749).next_gain(), 0.5);
+        // 짧으면 겹친 길이만큼 equal-power
+        a.fade_out.frames = 50;
+        let clips = timeline(&[a.clone(), b.clone()]);
+        let g = clip_envelope(&clips, &a, 949).next_gain();
+        assert!((g - FadeCurve::EqualPower.gain(0.5)).abs() < 1e-6);
+        assert_eq!(clip_envelope(&clips, &a, 899).next_gain(), 1.0);
+
+        // 다른 클립 안에 통째로 들어간 클립은 페이드 없이
+        let inner = clip(3, 200, 100);
+        let clips = timeline(&[clip(1, 0, 1_000), inner.clone()]);
+        assert_eq!(clip_envelope(&clips, &inner, 200).next_gain(), 1.0);
+    }
+}
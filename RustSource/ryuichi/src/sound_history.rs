@@ -0,0 +1,445 @@
+use crate::apply;
+use crate::unit::*;
+use crate::EditCommand;
+use crate::Engine;
+use crate::MixerParam;
+use crate::TrackList;
+use crate::{TempoEvent, TempoMap, TimeSignature};
+use std::collections::VecDeque;
+
+// -------------------------
+// 되돌리기/다시하기 기록
+// 한 단계 = 되돌릴 때 뒤에서부터 적용할 명령 목록
+// -------------------------
+pub enum HistoryOp {
+    Edit(EditCommand),
+    Bpm(f32),                               //BPM은 편집 큐를 안 타고 리버퍼로 바로 적용
+    Tempo(Vec<TempoEvent>),                 //템포 맵 이벤트 전체 (BPM과 같은 경로)
+    Meter(TimeSignature),                   //박자표 (재생과 무관, 값만 바꿈)
+    Mixer { track: u64, prev: MixerParam }, //믹서 값 (큐 밖에서 바로 적용)
+}
+
+pub struct History {
+    undo: VecDeque<Vec<HistoryOp>>,
+    redo: Vec<Vec<HistoryOp>>,
+    group_depth: u32,
+    group: Vec<HistoryOp>, //열려 있는 묶음
+}
+impl History {
+    pub fn new() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            group_depth: 0,
+            group: Vec::new(),
+        }
+    }
+
+    // 편집 하나의 되돌리기 명령(적용 순서대로) 기록, 새 편집이 들어오면 다시하기는 버림
+    pub fn record(&mut self, inverse: Vec<HistoryOp>) {
+        if inverse.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        if self.group_depth > 0 {
+            self.group.extend(inverse.into_iter().rev());
+        } else {
+            self.push_undo(inverse.into_iter().rev().collect());
+        }
+    }
+
+    fn push_undo(&mut self, step: Vec<HistoryOp>) {
+        if step.is_empty() {
+            return;
+        }
+        if self.undo.len() >= MAX_UNDO_STEPS {
+            self.undo.pop_front(); // 가장 오래된 단계부터 버림
+        }
+        self.undo.push_back(step);
+    }
+
+    pub fn begin_group(&mut self) {
+        self.group_depth += 1;
+    }
+
+    // 바깥 묶음이 닫힐 때 한 단계로 쌓음
+    pub fn end_group(&mut self) {
+        if self.group_depth == 0 {
+            return;
+        }
+        self.group_depth -= 1;
+        if self.group_depth == 0 {
+            let step = std::mem::take(&mut self.group);
+            self.push_undo(step);
+        }
+    }
+
+    // 묶음이 열린 채로 되돌리기를 부르면 거기까지를 한 단계로 닫음
+    fn close_group(&mut self) {
+        if self.group_depth > 0 {
+            self.group_depth = 1;
+            self.end_group();
+        }
+    }
+
+    // 샘플레이트 변경: 기록된 위치/길이를 새 레이트로 환산 (되돌려도 같은 자리)
+    pub fn rescale(&mut self, old_sr: u32, new_sr: u32) {
+        let steps = self.undo.iter_mut().chain(self.redo.iter_mut());
+        for op in steps.flatten().chain(self.group.iter_mut()) {
+            match op {
+                HistoryOp::Edit(cmd) => cmd.rescale(old_sr, new_sr),
+                HistoryOp::Tempo(events) => {
+                    for e in events.iter_mut() {
+                        e.frame = rescale_frames(e.frame, old_sr, new_sr);
+                    }
+                }
+                HistoryOp::Bpm(_) | HistoryOp::Meter(_) | HistoryOp::Mixer { .. } => {}
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.group_depth = 0;
+        self.group.clear();
+    }
+}
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn undo(&mut self) -> bool {
+        self.step_history(true)
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.step_history(false)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.with_history(|h, _| !h.undo.is_empty() || !h.group.is_empty())
+            .unwrap_or(false)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.with_history(|h, _| !h.redo.is_empty())
+            .unwrap_or(false)
+    }
+
+    // 밀린 편집을 지금 템포 기준으로 먼저 적용한 뒤 기록을 다룸
+    pub(crate) fn with_history<R>(
+        &self,
+        f: impl FnOnce(&mut History, &TrackList) -> R,
+    ) -> Option<R> {
+        self.edits.with_history(&self.tracks, &self.time_grid(), f)
+    }
+
+    // BPM 변경은 큐 밖에서 바로 적용되므로 직접 기록 (앞서 접수된 편집 뒤로 순서 맞춤)
+    pub(crate) fn record_bpm_change(&self, prev: f32) {
+        self.with_history(|h, _| h.record(vec![HistoryOp::Bpm(prev)]));
+    }
+
+    pub(crate) fn record_tempo_change(&self, prev: Vec<TempoEvent>) {
+        self.with_history(|h, _| h.record(vec![HistoryOp::Tempo(prev)]));
+    }
+
+    // 믹서 값은 바로 바꾸고 이전 값만 기록 (트랙 id가 없으면 false)
+    pub fn set_mixer(&self, track: u64, value: MixerParam) -> bool {
+        let Some(slot) = self.tracks.find(track) else {
+            return false;
+        };
+        let prev = slot.params.swap_mixer(value);
+        self.with_history(|h, _| h.record(vec![HistoryOp::Mixer { track, prev }]));
+        true
+    }
+
+    // 한 단계를 적용하고, 그 적용의 되돌리기 명령을 반대쪽 스택에 쌓음
+    fn step_history(&mut self, undo: bool) -> bool {
+        let cur = self.tempo();
+        let grid = self.time_grid();
+        let res = self.with_history(|h, tracks| {
+            h.close_group();
+            let step = if undo {
+                h.undo.pop_back()
+            } else {
+                h.redo.pop()
+            }?;
+            let mut opposite = Vec::new();
+            let mut bpm = None;
+            let mut tempo = None;
+            let mut clips = false;
+            for op in step.into_iter().rev() {
+                match op {
+                    HistoryOp::Edit(cmd) => match apply(cmd, tracks, &grid) {
+                        Some(inverse) => {
+                            clips = true;
+                            opposite.extend(inverse.into_iter().rev().map(HistoryOp::Edit))
+                        }
+                        None => eprintln!("[history] command rejected"),
+                    },
+                    HistoryOp::Bpm(b) => {
+                        opposite.push(HistoryOp::Bpm(bpm.unwrap_or(cur.base_bpm())));
+                        bpm = Some(b);
+                    }
+                    HistoryOp::Tempo(events) => {
+                        let prev = tempo.replace(events);
+                        opposite.push(HistoryOp::Tempo(
+                            prev.unwrap_or_else(|| cur.events().to_vec()),
+                        ));
+                    }
+                    HistoryOp::Meter(ts) => opposite.push(HistoryOp::Meter(self.meter.swap(ts))),
+                    HistoryOp::Mixer { track, prev } => match tracks.find(track) {
+                        Some(slot) => opposite.push(HistoryOp::Mixer {
+                            track,
+                            prev: slot.params.swap_mixer(prev),
+                        }),
+                        None => eprintln!("[history] track removed"),
+                    },
+                }
+            }
+            if undo {
+                h.redo.push(opposite);
+            } else {
+                h.push_undo(opposite);
+            }
+            Some((bpm, tempo, clips))
+        });
+        let Some(Some((bpm, tempo, clips))) = res else {
+            return false;
+        };
+        let tempo_changed = tempo.is_some();
+        if let Some(events) = tempo {
+            self.set_tempo_map(TempoMap::new(cur.base_bpm(), events));
+        }
+        // 템포나 클립이 바뀌었으면 이미 채운 버퍼를 버리고 다시 채움 (BPM도 같이면 한 번만)
+        if let Some(b) = bpm {
+            self.set_bpm(b);
+        } else if tempo_changed || clips {
+            self.rebuffer_current();
+        }
+        true
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_edit_undo(engine: *mut Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &mut *engine };
+    eng.undo()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_edit_redo(engine: *mut Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &mut *engine };
+    eng.redo()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_edit_can_undo(engine: *mut Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.can_undo()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_edit_can_redo(engine: *mut Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.can_redo()
+}
+
+// begin ~ end 사이에 보낸 편집은 되돌리기 한 번에 같이 되돌아감 (중첩 가능)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_edit_begin_group(engine: *mut Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.submit_edit(EditCommand::BeginGroup)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_edit_end_group(engine: *mut Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.submit_edit(EditCommand::EndGroup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    // 기록 내용 대신 구분용 BPM 값만 씀
+    fn op(n: f32) -> Vec<HistoryOp> {
+        vec![HistoryOp::Bpm(n)]
+    }
+
+    fn bpms(step: &[HistoryOp]) -> Vec<f32> {
+        step.iter()
+            .map(|op| match op {
+                HistoryOp::Bpm(b) => *b,
+                _ => f32::NAN,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_groups_make_one_step() {
+        let mut h = History::new();
+        h.record(op(1.0));
+        h.begin_group();
+        h.record(op(2.0));
+        h.begin_group();
+        h.record(op(3.0));
+        h.end_group();
+        assert_eq!(h.undo.len(), 1); // 안쪽만 닫혀서는 아직 안 쌓임
+        h.record(op(4.0));
+        h.end_group();
+        assert_eq!(h.undo.len(), 2);
+        // 되돌릴 때 뒤에서부터 적용하므로 기록 순서대로 들어 있음
+        assert_eq!(bpms(&h.undo[1]), vec![2.0, 3.0, 4.0]);
+
+        // 짝이 안 맞는 end는 무시, 빈 묶음은 단계가 안 생김
+        h.end_group();
+        h.begin_group();
+        h.end_group();
+        assert_eq!(h.undo.len(), 2);
+    }
+
+    #[test]
+    fn open_group_closes_on_undo() {
+        let mut h = History::new();
+        h.begin_group();
+        h.begin_group();
+        h.record(op(1.0));
+        h.close_group();
+        assert_eq!(h.group_depth, 0);
+        assert_eq!(h.undo.len(), 1);
+    }
+
+    #[test]
+    fn keeps_newest_steps_up_to_limit() {
+        let mut h = History::new();
+        for i in 0..MAX_UNDO_STEPS + 10 {
+            h.record(op(i as f32));
+        }
+        assert_eq!(h.undo.len(), MAX_UNDO_STEPS);
+        assert_eq!(bpms(&h.undo[0]), vec![10.0]);
+        assert_eq!(
+            bpms(h.undo.back().unwrap()),
+            vec![(MAX_UNDO_STEPS + 9) as f32]
+        );
+    }
+
+    fn volume(eng: &Engine, track: u64) -> f32 {
+        let slot = eng.tracks.find(track).unwrap();
+        f32::from_bits(slot.params.volume.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut eng = Engine::with_track_count(1).unwrap();
+        assert!(eng.set_mixer(0, MixerParam::Volume(0.3)));
+        assert!(eng.set_mixer(0, MixerParam::Pan(0.5)));
+        assert!(eng.undo());
+        assert!(eng.can_redo());
+        assert!(eng.redo());
+        assert!(eng.undo());
+        assert!(eng.set_mixer(0, MixerParam::Mute(true)));
+        assert!(!eng.can_redo());
+        assert!(!eng.redo());
+    }
+
+    fn muted(eng: &Engine, track: u64) -> bool {
+        let slot = eng.tracks.find(track).unwrap();
+        slot.params.muted.load(Ordering::Relaxed)
+    }
+
+    // 슬라이더 끌기는 UI가 begin/end 묶음으로 감싸서 한 단계
+    #[test]
+    fn mixer_drag_in_group_is_one_step() {
+        let mut eng = Engine::with_track_count(2).unwrap();
+        let start = volume(&eng, 0);
+        assert!(eng.submit_edit(EditCommand::BeginGroup));
+        for v in [0.5, 0.6, 0.7] {
+            assert!(eng.set_mixer(0, MixerParam::Volume(v)));
+        }
+        assert!(eng.submit_edit(EditCommand::EndGroup));
+        assert!(eng.set_mixer(1, MixerParam::Volume(0.9)));
+        assert!(eng.undo());
+        assert_eq!(volume(&eng, 0), 0.7);
+        assert!(eng.undo());
+        assert_eq!(volume(&eng, 0), start);
+        assert!(!eng.can_undo());
+    }
+
+    #[test]
+    fn separate_toggles_are_separate_steps() {
+        let mut eng = Engine::with_track_count(1).unwrap();
+        assert!(eng.set_mixer(0, MixerParam::Mute(true)));
+        assert!(eng.set_mixer(0, MixerParam::Mute(false)));
+        assert!(eng.undo());
+        assert!(muted(&eng, 0));
+        assert!(eng.can_undo());
+        assert!(eng.undo());
+        assert!(!muted(&eng, 0));
+        assert!(!eng.can_undo());
+    }
+
+    // 클립 편집을 되돌리면 이미 채운 버퍼를 다시 채움, 믹서만이면 그대로
+    #[test]
+    fn undo_of_clip_edit_rebuffers() {
+        let mut eng = Engine::with_track_count(1).unwrap();
+        let path = std::ffi::CString::new("missing.wav").unwrap();
+        let id = crate::rust_sound_add_clip(&mut eng, 0, path.as_ptr(), 0, 4800, 48000, 0, 0);
+        assert_ne!(id, 0);
+        assert!(eng.set_mixer(0, MixerParam::Volume(0.5)));
+        let epoch = eng.seek_epoch.load(Ordering::Acquire);
+        assert!(eng.undo());
+        assert_eq!(eng.seek_epoch.load(Ordering::Acquire), epoch);
+        assert!(eng.undo());
+        assert_eq!(eng.seek_epoch.load(Ordering::Acquire), epoch + 1);
+        assert!(eng.read_clip(id, |_| ()).is_none());
+        assert!(eng.redo());
+        assert_eq!(eng.seek_epoch.load(Ordering::Acquire), epoch + 2);
+        assert!(eng.read_clip(id, |_| ()).is_some());
+    }
+
+    // 샘플레이트를 바꿔도 기록은 남고, 되돌리면 새 레이트 기준 같은 자리로
+    #[test]
+    fn sample_rate_change_rescales_history() {
+        let mut eng = Engine::with_track_count(1).unwrap();
+        let path = std::ffi::CString::new("missing.wav").unwrap();
+        let id = crate::rust_sound_add_clip(&mut eng, 0, path.as_ptr(), 48_000, 4800, 48000, 0, 0);
+        assert!(crate::rust_sound_move_clip(&mut eng, id, 0, 96_000));
+        assert!(eng.change_sample_rate(44_100));
+        let start = |eng: &Engine| eng.read_clip(id, |c| (c.tl_start, c.tl_len));
+        assert_eq!(start(&eng), Some((88_200, 4410)));
+        assert!(eng.undo());
+        assert_eq!(start(&eng), Some((44_100, 4410)));
+        assert!(eng.redo());
+        assert_eq!(start(&eng), Some((88_200, 4410)));
+    }
+}
@@ -0,0 +1,280 @@
+use crate::unit::*;
+use crate::TrackParams;
+use std::f32::consts::FRAC_PI_2;
+use std::sync::atomic::Ordering;
+
+// -------------------------
+// 출력 스피커 배치
+// 채널 순서는 SMPTE/WAVE 순서 (5.1: L R C LFE Ls Rs)
+// -------------------------
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpeakerLayout {
+    Stereo,
+    Quad,
+    Surround51,
+}
+
+const NO_AZIMUTH: f32 = f32::NAN; // LFE처럼 패닝 대상이 아닌 채널
+
+impl SpeakerLayout {
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(Self::Stereo),
+            1 => Some(Self::Quad),
+            2 => Some(Self::Surround51),
+            _ => None,
+        }
+    }
+    pub fn code(self) -> u32 {
+        match self {
+            Self::Stereo => 0,
+            Self::Quad => 1,
+            Self::Surround51 => 2,
+        }
+    }
+    pub fn channels(self) -> usize {
+        self.azimuths().len()
+    }
+    // 채널별 방위각 (도, 0 = 정면, + = 오른쪽)
+    fn azimuths(self) -> &'static [f32] {
+        match self {
+            Self::Stereo => &[-30.0, 30.0],
+            Self::Quad => &[-45.0, 45.0, -135.0, 135.0],
+            Self::Surround51 => &[-30.0, 30.0, 0.0, NO_AZIMUTH, -110.0, 110.0],
+        }
+    }
+    // 스피커가 뒤쪽까지 둘러싸는지 (스테레오는 앞쪽 L~R 사이만)
+    fn surrounds(self) -> bool {
+        !matches!(self, Self::Stereo)
+    }
+}
+
+// 인접한 두 스피커 사이 constant-power 패닝 (gains는 채널 수만큼, 0으로 초기화 후 채움)
+pub fn pan_pairwise(layout: SpeakerLayout, azimuth: f32, gains: &mut [f32]) {
+    gains.fill(0.0);
+    let az = layout.azimuths();
+    let wrap = |a: f32| (a + 180.0).rem_euclid(360.0) - 180.0; // -180..180
+    let a = if azimuth.is_finite() {
+        wrap(azimuth)
+    } else {
+        0.0
+    };
+
+    // a를 시계방향으로 감싸는 가장 가까운 두 스피커 (왼쪽 lo, 오른쪽 hi)
+    let mut lo: Option<(usize, f32)> = None; // (채널, a까지 왼쪽으로 간 각도)
+    let mut hi: Option<(usize, f32)> = None; // (채널, a에서 오른쪽으로 간 각도)
+    for (ch, &s) in az.iter().enumerate() {
+        if s.is_nan() {
+            continue;
+        }
+        let left = (a - s).rem_euclid(360.0);
+        let right = (s - a).rem_euclid(360.0);
+        if lo.is_none_or(|(_, d)| left < d) {
+            lo = Some((ch, left));
+        }
+        if hi.is_none_or(|(_, d)| right < d) {
+            hi = Some((ch, right));
+        }
+    }
+    let (Some((lo_ch, lo_d)), Some((hi_ch, hi_d))) = (lo, hi) else {
+        return;
+    };
+
+    if !layout.surrounds() {
+        // 앞쪽만: L 바깥은 L, R 바깥은 R로 고정
+        let first = az[0];
+        let last = az[az.len() - 1];
+        if a <= first {
+            gains[0] = 1.0;
+            return;
+        }
+        if a >= last {
+            gains[az.len() - 1] = 1.0;
+            return;
+        }
+    }
+
+    let span = lo_d + hi_d;
+    if lo_ch == hi_ch || span <= f32::EPSILON {
+        gains[lo_ch] = 1.0;
+        return;
+    }
+    let t = (lo_d / span) * FRAC_PI_2;
+    gains[lo_ch] = t.cos();
+    gains[hi_ch] = t.sin();
+}
+
+// -------------------------
+// 트랙 하나의 출력 탭: (출력 채널, L 게인, R 게인)
+// -------------------------
+pub struct OutputTaps {
+    taps: [(usize, f32, f32); MAX_OUT_CHANNELS],
+    len: usize,
+}
+impl OutputTaps {
+    fn new() -> Self {
+        Self {
+            taps: [(0, 0.0, 0.0); MAX_OUT_CHANNELS],
+            len: 0,
+        }
+    }
+    fn push(&mut self, ch: usize, gl: f32, gr: f32) {
+        if self.len < MAX_OUT_CHANNELS && (gl != 0.0 || gr != 0.0) {
+            self.taps[self.len] = (ch, gl, gr);
+            self.len += 1;
+        }
+    }
+    pub fn as_slice(&self) -> &[(usize, f32, f32)] {
+        &self.taps[..self.len]
+    }
+}
+
+// 트랙 파라미터 + 레이아웃 → 탭 (뮤트/볼륨 0/범위 밖 채널이면 None)
+// layout이 None이면 채널 수만 있는 이산 출력: 메인은 0/1번 채널 스테레오
+pub fn track_taps(
+    params: &TrackParams,
+    layout: Option<SpeakerLayout>,
+    channels: usize,
+) -> Option<OutputTaps> {
+    let (gl, gr) = params.gains()?;
+    let mut taps = OutputTaps::new();
+    let route = params.route.load(Ordering::Relaxed);
+
+    if route >= 0 {
+        // 직접 지정: L → route, R → route+1 (한 채널만 남으면 모노로 합침)
+        let ch = route as usize;
+        if ch + 1 < channels {
+            taps.push(ch, gl, 0.0);
+            taps.push(ch + 1, 0.0, gr);
+        } else if ch < channels {
+            taps.push(ch, gl, gr);
+        } else {
+            return None;
+        }
+        return Some(taps);
+    }
+
+    match layout {
+        Some(layout) if layout.surrounds() => {
+            // L/R을 방위각 ±30도에 각각 놓고 인접 스피커 쌍으로 패닝
+            let azimuth = f32::from_bits(params.azimuth.load(Ordering::Relaxed));
+            let mut pl = [0.0f32; MAX_OUT_CHANNELS];
+            let mut pr = [0.0f32; MAX_OUT_CHANNELS];
+            pan_pairwise(layout, azimuth - 30.0, &mut pl[..channels]);
+            pan_pairwise(layout, azimuth + 30.0, &mut pr[..channels]);
+            for ch in 0..channels {
+                taps.push(ch, gl * pl[ch], gr * pr[ch]);
+            }
+        }
+        _ => {
+            // 스테레오(또는 이산 출력): 기존 L/R 팬 그대로
+            if channels >= 2 {
+                taps.push(0, gl, 0.0);
+                taps.push(1, 0.0, gr);
+            } else {
+                taps.push(0, gl, gr);
+            }
+        }
+    }
+    Some(taps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MixerParam, TrackConfig};
+
+    const LAYOUTS: [SpeakerLayout; 3] = [
+        SpeakerLayout::Stereo,
+        SpeakerLayout::Quad,
+        SpeakerLayout::Surround51,
+    ];
+
+    fn params(azimuth: f32) -> TrackParams {
+        let p = TrackParams::from_track(&TrackConfig::new().unwrap());
+        p.swap_mixer(MixerParam::Azimuth(azimuth));
+        p
+    }
+
+    #[test]
+    fn pairwise_gains_keep_power() {
+        let mut gains = [0.0f32; MAX_OUT_CHANNELS];
+        for layout in LAYOUTS {
+            let gains = &mut gains[..layout.channels()];
+            for i in -72..=72 {
+                let azimuth = i as f32 * 2.5;
+                pan_pairwise(layout, azimuth, gains);
+                let power: f32 = gains.iter().map(|g| g * g).sum();
+                assert!((power - 1.0).abs() < 1e-5, "{layout:?} {azimuth}");
+                assert!(gains.iter().filter(|&&g| g != 0.0).count() <= 2);
+            }
+        }
+    }
+
+    #[test]
+    fn speaker_azimuth_hits_one_speaker() {
+        let mut gains = [0.0f32; MAX_OUT_CHANNELS];
+        for layout in LAYOUTS {
+            let gains = &mut gains[..layout.channels()];
+            for (ch, &az) in layout.azimuths().iter().enumerate() {
+                if az.is_nan() {
+                    continue;
+                }
+                pan_pairwise(layout, az, gains);
+                for (c, &g) in gains.iter().enumerate() {
+                    let want = if c == ch { 1.0 } else { 0.0 };
+                    assert_eq!(g, want, "{layout:?} speaker {ch} channel {c}");
+                }
+            }
+        }
+        // 5.1의 LFE(3번)는 패닝 대상이 아님
+        let gains = &mut gains[..6];
+        pan_pairwise(SpeakerLayout::Surround51, 15.0, gains);
+        assert_eq!(gains[3], 0.0);
+        assert!(gains[0] == 0.0 && gains[2] > 0.0 && gains[1] > 0.0);
+        // 스테레오는 바깥쪽이면 끝 스피커에 고정
+        let gains = &mut gains[..2];
+        pan_pairwise(SpeakerLayout::Stereo, 120.0, gains);
+        assert_eq!(gains, &[0.0, 1.0]);
+    }
+
+    #[test]
+    fn surround_taps_split_each_side_by_power() {
+        for layout in [SpeakerLayout::Quad, SpeakerLayout::Surround51] {
+            for azimuth in [0.0, 45.0, -100.0, 180.0] {
+                let p = params(azimuth);
+                let (gl, gr) = p.gains().unwrap();
+                let taps = track_taps(&p, Some(layout), layout.channels()).unwrap();
+                let l: f32 = taps.as_slice().iter().map(|t| t.1 * t.1).sum();
+                let r: f32 = taps.as_slice().iter().map(|t| t.2 * t.2).sum();
+                assert!((l - gl * gl).abs() < 1e-5, "{layout:?} {azimuth}");
+                assert!((r - gr * gr).abs() < 1e-5, "{layout:?} {azimuth}");
+            }
+        }
+        // 정면 트랙의 L(-30도)은 쿼드 앞 L/R 사이, 뒤쪽은 0
+        let taps = track_taps(&params(0.0), Some(SpeakerLayout::Quad), 4).unwrap();
+        assert!(taps.as_slice().iter().all(|&(ch, _, _)| ch < 2));
+    }
+
+    #[test]
+    fn route_and_stereo_taps() {
+        let p = params(0.0);
+        let (gl, gr) = p.gains().unwrap();
+        let taps = track_taps(&p, Some(SpeakerLayout::Stereo), 2).unwrap();
+        assert_eq!(taps.as_slice(), &[(0, gl, 0.0), (1, 0.0, gr)]);
+
+        // 직접 지정: 두 채널, 마지막 채널이면 모노, 범위 밖이면 None
+        p.swap_mixer(MixerParam::Route(2));
+        let taps = track_taps(&p, Some(SpeakerLayout::Surround51), 6).unwrap();
+        assert_eq!(taps.as_slice(), &[(2, gl, 0.0), (3, 0.0, gr)]);
+        p.swap_mixer(MixerParam::Route(5));
+        let taps = track_taps(&p, Some(SpeakerLayout::Surround51), 6).unwrap();
+        assert_eq!(taps.as_slice(), &[(5, gl, gr)]);
+        p.swap_mixer(MixerParam::Route(6));
+        assert!(track_taps(&p, Some(SpeakerLayout::Surround51), 6).is_none());
+
+        p.swap_mixer(MixerParam::Route(-1));
+        p.swap_mixer(MixerParam::Mute(true));
+        assert!(track_taps(&p, None, 2).is_none());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::Engine;
+
+// -------------------------
+// 반복 구간 [start, end) (타임라인 프레임, 엔진 샘플레이트 기준)
+// 재생 위치는 반복을 펼친 좌표(스트림)로 계속 늘어나고, 타임라인 위치는 여기서 접어서 구함
+// 렌더는 되감기를 몰라도 되고, 워커는 되감는 지점 너머까지 미리 채워 둠 (리버퍼 없음)
+// -------------------------
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct LoopRegion {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl LoopRegion {
+    pub const NONE: Self = Self { start: 0, end: 0 };
+
+    pub fn is_active(&self) -> bool {
+        self.end > self.start
+    }
+
+    // 스트림 위치 → 타임라인 위치
+    #[inline]
+    pub fn fold(&self, stream: u64) -> u64 {
+        if !self.is_active() || stream < self.end {
+            return stream;
+        }
+        self.start + (stream - self.end) % (self.end - self.start)
+    }
+
+    // 다음 되감기까지 남은 frames (반복 안 하면 u64::MAX)
+    #[inline]
+    pub fn until_wrap(&self, stream: u64) -> u64 {
+        if !self.is_active() {
+            return u64::MAX;
+        }
+        if stream < self.end {
+            return self.end - stream;
+        }
+        let len = self.end - self.start;
+        len - (stream - self.end) % len
+    }
+}
+
+// 워커/오프라인 렌더가 보는 재생 위치 (스트림 좌표 + 지금 도는 반복 구간)
+#[derive(Clone, Copy, Debug)]
+pub struct Playhead {
+    pub stream: u64,
+    pub looped: LoopRegion,
+}
+
+impl Engine {
+    pub fn loop_region(&self) -> LoopRegion {
+        self.play_time_manager.loop_region()
+    }
+
+    pub fn loop_enabled(&self) -> bool {
+        self.play_time_manager.loop_enabled()
+    }
+
+    // 구간/켜짐 바꾸기: 워커가 아직 되감는 지점까지 안 썼으면 도는 구간만 바꾸고 (재생 중에도 끊김 없음)
+    // 아니면 지금 위치로 다시 seek (앞으로 되감을 지점이 달라지므로 링버퍼를 새로)
+    pub fn set_loop(&mut self, region: LoopRegion, enabled: bool) -> bool {
+        if !region.is_active() {
+            return false;
+        }
+        let pos = self.play_time_manager.pos_frames();
+        self.play_time_manager.set_loop(region, enabled);
+        if !self.retarget_loop() {
+            self.seek(pos);
+        }
+        true
+    }
+
+    // 옛 구간과 새 구간 모두 아직 되감지 않은 범위(스트림 = 타임라인)까지만 썼으면 그대로 이어짐
+    // 워커가 그 사이에 더 쓰지 못하게 타임라인을 다 잡은 채로 확인하고 바꿈
+    fn retarget_loop(&self) -> bool {
+        let tracks = self.tracks.snapshot();
+        let mut written = self.play_time_manager.stream_frames();
+        let mut held = Vec::with_capacity(tracks.len());
+        for slot in tracks.iter() {
+            let Ok(tr) = slot.timeline.lock() else {
+                return false;
+            };
+            written = written.max(tr.write_pos_frames);
+            held.push(tr);
+        }
+        let wraps_at = |r: LoopRegion| if r.is_active() { r.end } else { u64::MAX };
+        let pt = &self.play_time_manager;
+        let run = pt.loop_run_from(pt.stream_frames());
+        if written >= wraps_at(pt.loop_run()).min(wraps_at(run)) {
+            return false;
+        }
+        pt.set_loop_run(run);
+        true
+    }
+}
+
+// 반복 구간 설정: start < end, enabled = false면 구간만 기억
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_transport_set_loop(
+    engine: *mut Engine,
+    start: u64,
+    end: u64,
+    enabled: bool,
+) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &mut *engine };
+    eng.set_loop(LoopRegion { start, end }, enabled)
+}
+
+// 기억해 둔 구간으로 반복 켜기/끄기 (구간을 정한 적 없으면 false)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_transport_set_loop_enabled(engine: *mut Engine, enabled: bool) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &mut *engine };
+    let region = eng.loop_region();
+    eng.set_loop(region, enabled)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_transport_loop_enabled(engine: *const Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.loop_enabled()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_transport_loop_start(engine: *const Engine) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.loop_region().start
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_transport_loop_end(engine: *const Engine) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.loop_region().end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    const R: LoopRegion = LoopRegion {
+        start: 100,
+        end: 400,
+    };
+
+    #[test]
+    fn fold_wraps_modulo_length() {
+        assert_eq!(R.fold(0), 0);
+        assert_eq!(R.fold(399), 399);
+        // 끝에 닿는 순간 구간 처음으로
+        assert_eq!(R.fold(400), 100);
+        assert_eq!(R.fold(401), 101);
+        assert_eq!(R.fold(699), 399);
+        assert_eq!(R.fold(700), 100);
+        // 여러 바퀴
+        assert_eq!(R.fold(400 + 300 * 1_000_000 + 17), 117);
+        assert_eq!(LoopRegion::NONE.fold(12_345), 12_345);
+    }
+
+    #[test]
+    fn until_wrap_counts_to_next_end() {
+        assert_eq!(R.until_wrap(0), 400);
+        assert_eq!(R.until_wrap(399), 1);
+        assert_eq!(R.until_wrap(400), 300);
+        assert_eq!(R.until_wrap(699), 1);
+        assert_eq!(R.until_wrap(700), 300);
+        assert_eq!(R.until_wrap(400 + 300 * 1_000_000 + 17), 283);
+        assert_eq!(LoopRegion::NONE.until_wrap(5), u64::MAX);
+        // 되감기 직전까지 접은 위치는 구간 끝으로 이어짐
+        for s in [0, 250, 400, 555, 9_999] {
+            assert_eq!(R.fold(s) + R.until_wrap(s), R.end);
+        }
+    }
+
+    #[test]
+    fn set_loop_before_wrap_keeps_stream() {
+        let mut eng = Engine::with_track_count(1).unwrap();
+        let epoch = eng.seek_epoch.load(Ordering::Acquire);
+        let wide = LoopRegion {
+            start: 0,
+            end: 1_000_000,
+        };
+        assert!(eng.set_loop(wide, true));
+        assert!(eng.set_loop(wide, false));
+        assert!(eng.set_loop(R, true));
+        assert_eq!(eng.play_time_manager.loop_run(), R);
+        assert_eq!(eng.seek_epoch.load(Ordering::Acquire), epoch);
+
+        // 이미 되감는 지점 너머까지 써 둔 게 있으면 다시 seek
+        let slot = eng.tracks.snapshot()[0].clone();
+        slot.timeline.lock().unwrap().write_pos_frames = 500;
+        assert!(eng.set_loop(wide, true));
+        assert_eq!(eng.play_time_manager.loop_run(), wide);
+        assert!(eng.seek_epoch.load(Ordering::Acquire) > epoch);
+        assert!(!eng.set_loop(LoopRegion::NONE, true));
+    }
+}
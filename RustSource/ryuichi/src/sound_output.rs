@@ -0,0 +1,456 @@
+use crate::unit::*;
+use crate::Engine;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    BufferSize, FromSample, SampleFormat, SampleRate, SizedSample, StreamConfig, StreamError,
+    SupportedBufferSize,
+};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const SCRATCH_FRAMES: usize = 8_192; // 콜백 한 번에 렌더하는 최대 프레임 (넘으면 나눠서)
+const RESTART_RETRY: Duration = Duration::from_millis(500); // 장치 분실 후 재시도 간격
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct OutputInfo {
+    pub sample_rate: u32,
+    pub buffer_frames: u32, // 0 = 장치 기본값 (크기 모름)
+    pub channels: u16,
+}
+
+struct OutputRequest {
+    device: Option<String>, // None = 기본 장치
+    sample_rate: u32,       // 0 = 장치 기본값
+    buffer_frames: u32,     // 0 = 장치 기본값
+    channels: u16,          // 엔진 출력 레이아웃 채널 수
+}
+
+enum OutputMsg {
+    DeviceLost(u64), //스트림 세대 번호
+    Stop,
+}
+
+// 오디오 콜백/감시 스레드로 엔진 포인터를 넘기기 위한 래퍼
+// 콜백은 이걸로 &mut Engine을 만들어 render_channels를 부름: 렌더 상태를 만지는 콜백은 하나뿐이어야 하므로
+// JUCE 호스트 콜백(rust_render_interleaved)과 이 출력은 동시에 못 씀 (start_output이 거부)
+#[derive(Clone, Copy)]
+struct EnginePtr(*mut Engine);
+unsafe impl Send for EnginePtr {}
+impl EnginePtr {
+    fn get(self) -> *mut Engine {
+        self.0
+    }
+}
+
+// -------------------------
+// 장치 목록
+// -------------------------
+pub fn output_device_names() -> Vec<String> {
+    let host = cpal::default_host();
+    match host.output_devices() {
+        Ok(devs) => devs.filter_map(|d| d.name().ok()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn find_device(host: &cpal::Host, name: Option<&str>) -> Option<cpal::Device> {
+    match name {
+        None => host.default_output_device(),
+        Some(name) => host
+            .output_devices()
+            .ok()?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false)),
+    }
+}
+
+// -------------------------
+// 샘플레이트/버퍼 크기 협상
+// -------------------------
+fn negotiate(
+    device: &cpal::Device,
+    req: &OutputRequest,
+) -> Result<(StreamConfig, SampleFormat), String> {
+    let default = device.default_output_config().map_err(|e| e.to_string())?;
+    let want_sr = if req.sample_rate == 0 {
+        default.sample_rate().0
+    } else {
+        req.sample_rate
+    };
+
+    // 원하는 샘플레이트를 지원하는 것 중 레이아웃 채널 수 그대로 > 더 많은 채널 > 스테레오 > 모노, f32 우선
+    let want_ch = req.channels.max(1);
+    let score = |ch: u16, fmt: SampleFormat| {
+        let ch_score = match ch {
+            c if c == want_ch => 0,
+            c if c > want_ch && c as usize <= MAX_OUT_CHANNELS => 1,
+            2 => 2,
+            _ => 3,
+        };
+        let fmt_score = if fmt == SampleFormat::F32 { 0 } else { 1 };
+        ch_score * 2 + fmt_score
+    };
+    let best = device
+        .supported_output_configs()
+        .map_err(|e| e.to_string())?
+        .filter(|r| r.min_sample_rate().0 <= want_sr && want_sr <= r.max_sample_rate().0)
+        .filter(|r| is_supported_format(r.sample_format()))
+        .min_by_key(|r| score(r.channels(), r.sample_format()))
+        .map(|r| r.with_sample_rate(SampleRate(want_sr)));
+    let chosen = match best {
+        Some(c) => c,
+        None => default, // 요청한 샘플레이트를 못 쓰면 장치 기본값
+    };
+
+    let buffer_size = match (*chosen.buffer_size(), req.buffer_frames) {
+        (_, 0) => BufferSize::Default,
+        (SupportedBufferSize::Range { min, max }, n) => BufferSize::Fixed(n.clamp(min, max)),
+        (SupportedBufferSize::Unknown, _) => BufferSize::Default,
+    };
+    let mut config = chosen.config();
+    config.buffer_size = buffer_size;
+    Ok((config, chosen.sample_format()))
+}
+
+fn is_supported_format(fmt: SampleFormat) -> bool {
+    matches!(
+        fmt,
+        SampleFormat::F32 | SampleFormat::I16 | SampleFormat::U16 | SampleFormat::I32
+    )
+}
+
+// -------------------------
+// 스트림 생성
+// -------------------------
+fn build_stream<T>(
+    engine: EnginePtr,
+    device: &cpal::Device,
+    config: &StreamConfig,
+    generation: u64,
+    tx: mpsc::Sender<OutputMsg>,
+) -> Result<cpal::Stream, String>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let dev_ch = config.channels as usize;
+    // 엔진이 장치 채널 수 그대로 렌더 (레이아웃과 같으면 서라운드 패닝, 아니면 0/1번이 메인)
+    let render_ch = dev_ch.min(MAX_OUT_CHANNELS);
+    let mut scratch = vec![0.0f32; SCRATCH_FRAMES * render_ch];
+    let data_cb = move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+        let eng = unsafe { &mut *engine.get() };
+        let frames = data.len() / dev_ch;
+        let mut done = 0usize;
+        while done < frames {
+            let n = (frames - done).min(SCRATCH_FRAMES);
+            let buf = &mut scratch[..n * render_ch];
+            eng.render_channels(buf, render_ch);
+            for f in 0..n {
+                let frame = &mut data[(done + f) * dev_ch..(done + f + 1) * dev_ch];
+                for (c, s) in frame.iter_mut().enumerate() {
+                    let v = if c < render_ch {
+                        buf[f * render_ch + c]
+                    } else {
+                        0.0
+                    };
+                    *s = T::from_sample(v);
+                }
+            }
+            done += n;
+        }
+    };
+    let err_cb = move |err: StreamError| {
+        eprintln!("[cpal output] stream error: {err}");
+        if matches!(err, StreamError::DeviceNotAvailable) {
+            let _ = tx.send(OutputMsg::DeviceLost(generation));
+        }
+    };
+    device
+        .build_output_stream(config, data_cb, err_cb, None)
+        .map_err(|e| e.to_string())
+}
+
+fn open_stream(
+    host: &cpal::Host,
+    engine: EnginePtr,
+    req: &OutputRequest,
+    allow_default_fallback: bool,
+    generation: u64,
+    tx: &mpsc::Sender<OutputMsg>,
+) -> Result<(cpal::Stream, OutputInfo), String> {
+    let device = find_device(host, req.device.as_deref())
+        .or_else(|| {
+            if allow_default_fallback {
+                host.default_output_device()
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| "output device not found".to_string())?;
+    let (config, format) = negotiate(&device, req)?;
+
+    // 스트림이 돌기 전에 엔진 샘플레이트를 장치에 맞춤 (위치 환산 + 리버퍼)
+    // 같은 레이트로 다시 열 때(장치 분실 후 재시작)는 건드리지 않음
+    let eng = unsafe { &*engine.get() };
+    if eng.play_time_manager.sr() != config.sample_rate.0 {
+        eng.change_sample_rate(config.sample_rate.0);
+    }
+
+    let tx = tx.clone();
+    let stream = match format {
+        SampleFormat::F32 => build_stream::<f32>(engine, &device, &config, generation, tx),
+        SampleFormat::I16 => build_stream::<i16>(engine, &device, &config, generation, tx),
+        SampleFormat::U16 => build_stream::<u16>(engine, &device, &config, generation, tx),
+        SampleFormat::I32 => build_stream::<i32>(engine, &device, &config, generation, tx),
+        other => Err(format!("unsupported sample format: {other}")),
+    }?;
+    stream.play().map_err(|e| e.to_string())?;
+
+    let info = OutputInfo {
+        sample_rate: config.sample_rate.0,
+        buffer_frames: match config.buffer_size {
+            BufferSize::Fixed(n) => n,
+            BufferSize::Default => 0,
+        },
+        channels: config.channels,
+    };
+    Ok((stream, info))
+}
+
+// -------------------------
+// 출력 감시 스레드: 스트림을 소유하고, 장치 분실 시 다시 연다
+// -------------------------
+pub struct CpalOutput {
+    tx: mpsc::Sender<OutputMsg>,
+    handle: Option<JoinHandle<()>>,
+    info: Arc<Mutex<OutputInfo>>,
+    restarts: Arc<AtomicU64>,
+}
+
+impl CpalOutput {
+    fn start(engine: EnginePtr, req: OutputRequest) -> Result<Self, String> {
+        let (tx, rx) = mpsc::channel::<OutputMsg>();
+        let (ready_tx, ready_rx) = mpsc::sync_channel::<Result<(), String>>(1);
+        let info = Arc::new(Mutex::new(OutputInfo::default()));
+        let restarts = Arc::new(AtomicU64::new(0));
+
+        let tx_c = tx.clone();
+        let info_c = Arc::clone(&info);
+        let restarts_c = Arc::clone(&restarts);
+        let handle = thread::spawn(move || {
+            let host = cpal::default_host();
+            let mut generation = 0u64;
+            let mut stream = match open_stream(&host, engine, &req, false, generation, &tx_c) {
+                Ok((s, i)) => {
+                    if let Ok(mut g) = info_c.lock() {
+                        *g = i;
+                    }
+                    let _ = ready_tx.send(Ok(()));
+                    Some(s)
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            loop {
+                match rx.recv() {
+                    Ok(OutputMsg::DeviceLost(g)) if g == generation => {
+                        stream = None; //끊긴 스트림 정리
+                        generation += 1;
+                        // 같은 장치 → 없으면 기본 장치로 다시 열릴 때까지 재시도
+                        while stream.is_none() {
+                            match rx.recv_timeout(RESTART_RETRY) {
+                                Ok(OutputMsg::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                                    return;
+                                }
+                                _ => {}
+                            }
+                            match open_stream(&host, engine, &req, true, generation, &tx_c) {
+                                Ok((s, i)) => {
+                                    if let Ok(mut g) = info_c.lock() {
+                                        *g = i;
+                                    }
+                                    restarts_c.fetch_add(1, Ordering::Relaxed);
+                                    stream = Some(s);
+                                }
+                                Err(e) => eprintln!("[cpal output] restart failed: {e}"),
+                            }
+                        }
+                    }
+                    Ok(OutputMsg::DeviceLost(_)) => {} //이전 스트림에서 온 늦은 알림
+                    Ok(OutputMsg::Stop) | Err(_) => break,
+                }
+            }
+            drop(stream);
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                tx,
+                handle: Some(handle),
+                info,
+                restarts,
+            }),
+            Ok(Err(e)) => {
+                let _ = handle.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = handle.join();
+                Err("output thread exited".to_string())
+            }
+        }
+    }
+
+    pub fn info(&self) -> OutputInfo {
+        self.info.lock().map(|g| *g).unwrap_or_default()
+    }
+
+    pub fn restart_count(&self) -> u64 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for CpalOutput {
+    fn drop(&mut self) {
+        let _ = self.tx.send(OutputMsg::Stop);
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+impl Engine {
+    // 엔진이 직접 cpal 스트림을 연다. 엔진은 스트림이 도는 동안 주소가 바뀌면 안 됨(Box 등)
+    // JUCE 콜백이 렌더를 돌리는 중이면 거부 (한 엔진에 렌더 콜백은 하나)
+    pub fn start_output(
+        &mut self,
+        device: Option<&str>,
+        sample_rate: u32,
+        buffer_frames: u32,
+    ) -> Result<OutputInfo, String> {
+        if self.host_render.load(Ordering::Acquire) {
+            return Err("host audio callback is driving the engine".to_string());
+        }
+        self.stop_output();
+        let req = OutputRequest {
+            device: device.map(|s| s.to_string()),
+            sample_rate,
+            buffer_frames,
+            channels: self.output_layout().channels() as u16,
+        };
+        let out = CpalOutput::start(EnginePtr(self as *mut Engine), req)?;
+        let info = out.info();
+        self.output = Some(out);
+        Ok(info)
+    }
+
+    pub fn stop_output(&mut self) {
+        // Drop에서 감시 스레드 종료 + 스트림 해제
+        self.output = None;
+    }
+
+    pub fn output_info(&self) -> Option<OutputInfo> {
+        self.output.as_ref().map(|o| o.info())
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rust_output_device_count() -> u32 {
+    output_device_names().len() as u32
+}
+
+// 장치 이름을 out(cap 바이트)에 NUL 포함해서 복사, 반환값은 NUL 제외 길이 (실패 시 0)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_output_device_name(index: u32, out: *mut c_char, cap: usize) -> usize {
+    if out.is_null() || cap == 0 {
+        return 0;
+    }
+    let names = output_device_names();
+    let Some(name) = names.get(index as usize) else {
+        return 0;
+    };
+    let bytes = name.as_bytes();
+    let n = bytes.len().min(cap - 1);
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), out as *mut u8, n);
+        *out.add(n) = 0;
+    }
+    n
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_output_start(
+    engine: *mut Engine,
+    device: *const c_char,
+    sample_rate: u32,
+    buffer_frames: u32,
+) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &mut *engine };
+    // device == null 이면 기본 장치
+    let name = if device.is_null() {
+        None
+    } else {
+        match unsafe { CStr::from_ptr(device) }.to_str() {
+            Ok(s) => Some(s),
+            Err(_) => return false,
+        }
+    };
+    match eng.start_output(name, sample_rate, buffer_frames) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("[rust_output_start] {e}");
+            false
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_output_stop(engine: *mut Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &mut *engine };
+    eng.stop_output();
+    true
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_output_is_running(engine: *const Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.output.is_some()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_output_buffer_frames(engine: *const Engine) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.output_info().map(|i| i.buffer_frames).unwrap_or(0)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_output_restart_count(engine: *const Engine) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.output.as_ref().map(|o| o.restart_count()).unwrap_or(0)
+}
@@ -1,17 +1,22 @@
+use crate::track_taps;
 use crate::unit::*;
 use crate::Clip;
 use crate::DecoderState;
 use crate::Engine;
+use crate::SourceWindow;
 use crate::TrackTimeline;
-pub use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-pub use cpal::SampleFormat;
+use crate::{ClipEnvelope, ClipKey, ClipVoice, Fade, FadeCurve, TrackVoices};
+use crate::{PitchMode, StretchParams, StretchSource, Stretcher, TempoMode};
+use crate::{Playhead, Rate, TempoMap};
+use crate::{RenderGarbage, RenderMsg, RenderState, SfxState};
 pub use rtrb::{Consumer, Producer, RingBuffer};
+use std::collections::BTreeMap;
 pub use std::{
     ffi::CStr,
     fs::File,
     path::Path,
     sync::{
-        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, AtomicUsize, Ordering},
         mpsc, Arc, Mutex,
     },
     thread::{self, JoinHandle},
@@ -27,12 +32,9 @@ pub use symphonia::core::{
     units::Time,
 };
 pub use symphonia::default::{get_codecs, get_probe};
-pub use windows::Win32::System::Threading::{
-    GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_ABOVE_NORMAL,
-    THREAD_PRIORITY_TIME_CRITICAL,
-};
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_sound_play(engine: *mut Engine) -> bool {
     if engine.is_null() {
         return false;
@@ -42,11 +44,10 @@ pub extern "C" fn rust_sound_play(engine: *mut Engine) -> bool {
     eng.with_seek_lock(|eng| {
         // 1) 엔진 상태 정렬
         eng.pause_workers();
-        eng.align_write_pos_to_transport();
-        eng.flush_ringbuffers();
+        let epoch = eng.begin_rebuffer();
 
-        // 2) RB1만 프리필
-        let _ = eng.prefill_rb1_blocking(PREFILL_ON_START);
+        // 2) RB1 새로 만들고 high 워터마크까지 프리필 → 렌더로 넘김
+        eng.finish_rebuffer(epoch);
     });
 
     // 3) 재생 시작
@@ -56,6 +57,7 @@ pub extern "C" fn rust_sound_play(engine: *mut Engine) -> bool {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_sound_stop(engine: *mut Engine) -> bool {
     if engine.is_null() {
         return false;
@@ -65,41 +67,44 @@ pub extern "C" fn rust_sound_stop(engine: *mut Engine) -> bool {
     eng.with_seek_lock(|eng| {
         eng.play_time_manager.stop();
         eng.pause_workers();
-        eng.flush_ringbuffers(); // 선택: 멈출 때 비워두면 다음 시작이 깔끔
+        // 남은 버퍼는 다음 play에서 새 링버퍼로 교체됨
     });
 
     true
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_sound_seek(engine: *mut Engine, pos_frames: u64) -> bool {
     if engine.is_null() {
         return false;
     }
     let eng = unsafe { &mut *engine };
-    let was_playing = eng.play_time_manager.in_playing();
+    eng.seek(pos_frames);
+    true
+}
 
-    eng.with_seek_lock(|eng| {
-        if was_playing {
-            eng.play_time_manager.stop();
-        }
-        eng.pause_workers();
+impl Engine {
+    pub fn seek(&mut self, pos_frames: u64) {
+        let was_playing = self.play_time_manager.in_playing();
 
-        // 재생 위치 이동 + 타임라인 정렬
-        eng.play_time_manager.seek_frames(pos_frames);
-        eng.align_write_pos_to_transport();
-        eng.seek_epoch.fetch_add(1, Ordering::Release);
+        self.with_seek_lock(|eng| {
+            if was_playing {
+                eng.play_time_manager.stop();
+            }
+            eng.pause_workers();
+            let epoch = eng.begin_rebuffer();
 
-        // RB1만 초기화/프리필
-        eng.flush_ringbuffers();
-        let _ = eng.prefill_rb1_blocking(PREFILL_ON_SEEK);
-    });
+            // 재생 위치 이동 후 RB1 새로 만들고 프리필
+            eng.play_time_manager.seek_frames(pos_frames);
+            eng.finish_rebuffer(epoch);
+        });
 
-    if was_playing {
-        eng.play_time_manager.start();
-        eng.wake_workers();
+        if was_playing {
+            self.play_time_manager.start();
+            self.wake_workers();
+        }
     }
-    true
 }
 
 // -------------------------
@@ -107,106 +112,170 @@ pub extern "C" fn rust_sound_seek(engine: *mut Engine, pos_frames: u64) -> bool
 // -------------------------
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_render_interleaved(
     engine: *mut Engine,
     out_ptr: *mut f32,
     frames: usize,
     channels: i32,
 ) -> usize {
-    if engine.is_null() || out_ptr.is_null() || frames == 0 || channels != 2 {
+    if engine.is_null() || out_ptr.is_null() || frames == 0 {
         return 0;
     }
+    let channels = match usize::try_from(channels) {
+        Ok(c) if (1..=MAX_OUT_CHANNELS).contains(&c) => c,
+        _ => return 0,
+    };
     let eng = unsafe { &mut *engine };
-    let ch = channels as usize;
-    if ch != 2 {
-        return 0;
-    }
-    let mut zeros = 0u64;
-    let mut had_underrun = false;
-
-    let out: &mut [f32] = unsafe { std::slice::from_raw_parts_mut(out_ptr, frames * ch) };
-    out.fill(0.0);
-
-    if let Ok(mut st) = eng.sfx_state.lock() {
-        if let Some(sfx) = st.as_mut() {
-            let total = sfx.sample.nframes as usize;
-            let start = sfx.frame;
-            if start < total {
-                let to_copy = (total - start).min(frames);
-                let src = &sfx.sample.data[start * 2..(start + to_copy) * 2];
-
-                // 간단 페이드인(클릭 방지). 필요없으면 통째로 copy_from_slice만 쓰세요.
-                const FADE: usize = 64;
-                if start == 0 {
-                    let n = to_copy.min(FADE);
-                    for i in 0..n {
-                        let g = i as f32 / FADE as f32;
-                        out[i * 2] = (src[i * 2] * g).clamp(-1.0, 1.0);
-                        out[i * 2 + 1] = (src[i * 2 + 1] * g).clamp(-1.0, 1.0);
+    let out: &mut [f32] = unsafe { std::slice::from_raw_parts_mut(out_ptr, frames * channels) };
+    eng.render_channels(out, channels)
+}
+
+impl RenderState {
+    // 콜백 맨 앞에서 컨트롤 메시지 반영 (락/할당 없음, 버릴 것은 trash로)
+    fn apply_messages(&mut self) {
+        // 리버퍼를 먼저 집어야 그보다 앞서 보낸 AddTrack이 아래 큐 비우기에서 반드시 보임
+        let rebuffer = self.rebuffer.take();
+        while let Ok(msg) = self.inbox.pop() {
+            match msg {
+                RenderMsg::AddTrack(t) => {
+                    if self.tracks.len() < self.tracks.capacity() {
+                        self.tracks.push(t);
+                    } else {
+                        self.discard(RenderGarbage::Track(t));
                     }
-                    if to_copy > n {
-                        out[n * 2..to_copy * 2].copy_from_slice(&src[n * 2..to_copy * 2]);
+                }
+                RenderMsg::RemoveTrack(id) => {
+                    if let Some(i) = self.tracks.iter().position(|t| t.slot.id == id) {
+                        let t = self.tracks.swap_remove(i);
+                        self.discard(RenderGarbage::Track(t));
                     }
-                } else {
-                    out[..to_copy * 2].copy_from_slice(src);
                 }
-
-                sfx.frame += to_copy;
-                if sfx.frame >= total {
-                    *st = None; // 끝났으면 소거
+                RenderMsg::PadOn(sample) => {
+                    let old = self.sfx.replace(SfxState { sample, frame: 0 });
+                    if let Some(old) = old {
+                        self.discard(RenderGarbage::Sfx(old));
+                    }
+                }
+                RenderMsg::PadOff => {
+                    if let Some(old) = self.sfx.take() {
+                        self.discard(RenderGarbage::Sfx(old));
+                    }
                 }
-            } else {
-                *st = None;
             }
         }
+        if let Some(mut rb) = rebuffer {
+            for (id, cons) in rb.consumers.iter_mut() {
+                if let Some(t) = self.tracks.iter_mut().find(|t| t.slot.id == *id) {
+                    std::mem::swap(&mut t.consumer, cons);
+                }
+            }
+            // rb.consumers 안에는 이제 옛 컨슈머들이 들어있음
+            self.epoch = rb.epoch;
+            self.discard(RenderGarbage::Rebuffer(rb));
+        }
     }
 
-    // 재생 중이 아니면 무음
-    if !eng.play_time_manager.in_playing() {
-        return frames;
+    fn discard(&mut self, g: RenderGarbage) {
+        // 오디오 스레드에서는 해제하지 않음 (RENDER_TRASH_CAPACITY가 넘치지 않게 잡혀 있음)
+        let pushed = self.trash.push(g).is_ok();
+        debug_assert!(pushed, "render trash overflow");
     }
 
-    // 리버퍼 중이면 이번 콜백은 무음으로 패스(클릭 방지)
-    let seek_arc = std::sync::Arc::clone(&eng.seek_lock);
-    let _guard = match seek_arc.try_lock() {
-        Ok(g) => g,
-        Err(_) => {
-            return frames;
+    fn mix_pad(&mut self, out: &mut [f32], frames: usize, channels: usize) {
+        let Some(sfx) = self.sfx.as_mut() else {
+            return;
+        };
+        let total = sfx.sample.nframes as usize;
+        let start = sfx.frame;
+        if start < total {
+            let to_copy = (total - start).min(frames);
+            let src = &sfx.sample.data[start * 2..(start + to_copy) * 2];
+
+            // 간단 페이드인(클릭 방지). 패드는 앞 두 채널(L/R)로만, 모노 출력이면 합침
+            const FADE: usize = 64;
+            for i in 0..to_copy {
+                let g = if start == 0 && i < FADE {
+                    i as f32 / FADE as f32
+                } else {
+                    1.0
+                };
+                let (l, r) = (src[i * 2] * g, src[i * 2 + 1] * g);
+                if channels >= 2 {
+                    out[i * channels] = l.clamp(-1.0, 1.0);
+                    out[i * channels + 1] = r.clamp(-1.0, 1.0);
+                } else {
+                    out[i] = ((l + r) * 0.5).clamp(-1.0, 1.0);
+                }
+            }
+
+            sfx.frame += to_copy;
+            if sfx.frame < total {
+                return;
+            }
         }
-    };
+        // 끝났으면 소거
+        if let Some(old) = self.sfx.take() {
+            self.discard(RenderGarbage::Sfx(old));
+        }
+    }
+}
 
-    // 파라미터 핸들
-    let params = &eng.real_time_params;
+impl Engine {
+    // out: L/R 인터리브드 버퍼, 반환값은 채운 프레임 수
+    pub fn render_interleaved(&mut self, out: &mut [f32]) -> usize {
+        self.render_channels(out, CHANNELS)
+    }
 
-    // 트랙들을 RB1(컨슈머: f32, L/R 인터리브드)에서 직접 mix
-    for (ti, cons_mx) in eng.consumers.iter().enumerate() {
-        // 파라 미스매치 가드
-        if ti >= params.volume.len() || ti >= params.pan.len() || ti >= params.muted.len() {
-            continue;
+    // out: channels개 채널 인터리브드 버퍼, 반환값은 채운 프레임 수
+    // 채널 수가 설정된 레이아웃과 같으면 그 레이아웃으로 패닝, 아니면 이산 출력(0/1번이 메인 L/R)
+    // 오디오 콜백 전용: 락을 잡지 않고, 컨트롤 변경은 render 메시지로만 받음
+    pub fn render_channels(&mut self, out: &mut [f32], channels: usize) -> usize {
+        if channels == 0 || channels > MAX_OUT_CHANNELS {
+            return 0;
         }
+        let frames = out.len() / channels;
+        if frames == 0 {
+            return 0;
+        }
+        let layout = Some(self.output_layout()).filter(|l| l.channels() == channels);
+        let mut zeros = 0u64;
+        let mut had_underrun = false;
 
-        let muted = params.muted[ti].load(Ordering::Relaxed);
-        let vol = f32::from_bits(params.volume[ti].load(Ordering::Relaxed)).clamp(0.0, 1.0);
-        let pan = f32::from_bits(params.pan[ti].load(Ordering::Relaxed)).clamp(-1.0, 1.0);
+        out.fill(0.0);
 
-        if muted || vol == 0.0 {
-            // 이 트랙은 스킵 (버퍼는 남겨둠: 추후 재생 재개 시 팝)
-            continue;
+        let render = &mut self.render;
+        render.apply_messages();
+        render.mix_pad(out, frames, channels);
+
+        // 재생 중이 아니면 무음
+        if !self.play_time_manager.in_playing() {
+            return frames;
         }
 
-        // 간단한 equal-power가 아닌 linear-pan (요청 내용 유지)
-        let gl = vol * (1.0 - pan) * 0.5;
-        let gr = vol * (1.0 + pan) * 0.5;
-        let mut underrun_any = false;
-        if let Ok(mut cons) = cons_mx.lock() {
+        // 리버퍼 중이면(새 컨슈머를 아직 못 받음) 이번 콜백은 무음으로 패스(클릭 방지)
+        if render.epoch != self.seek_epoch.load(Ordering::Acquire) {
+            return frames;
+        }
+        let start_pos = self.play_time_manager.stream_frames();
+
+        // 트랙들을 RB1(컨슈머: f32, L/R 인터리브드)에서 직접 mix
+        for t in render.tracks.iter_mut() {
+            let Some(taps) = track_taps(&t.slot.params, layout, channels) else {
+                // 이 트랙은 스킵 (버퍼는 남겨둠: 추후 재생 재개 시 팝)
+                continue;
+            };
+            let taps = taps.as_slice();
+            let cons = &mut t.consumer;
+            let mut underrun_any = false;
             for f in 0..frames {
                 let l = match cons.pop() {
                     Ok(v) => v,
                     Err(_) => {
                         underrun_any = true;
-                        had_underrun = true; 
+                        had_underrun = true;
                         zeros += 1;
-                         0.0
+                        0.0
                     }
                 };
                 let r = match cons.pop() {
@@ -214,32 +283,48 @@ pub extern "C" fn rust_render_interleaved(
                     Err(_) => {
                         underrun_any = true;
                         had_underrun = true;
-                         zeros += 1;
-                          0.0
+                        zeros += 1;
+                        0.0
                     }
                 };
 
-                let i = f * 2;
-                unsafe {
-                    *out.get_unchecked_mut(i) += l * gl; // L
-                    *out.get_unchecked_mut(i + 1) += r * gr; // R
+                let i = f * channels;
+                for &(ch, gl, gr) in taps {
+                    unsafe {
+                        *out.get_unchecked_mut(i + ch) += l * gl + r * gr;
+                    }
                 }
             }
+            // low 워터마크 밑이면 담당 워커 깨우기 (이미 요청돼 있으면 생략)
+            let cap = cons.buffer().capacity() / CHANNELS;
+            let (low, _) = watermarks(cap);
+            if (underrun_any || cons.slots() / CHANNELS < low)
+                && !t.slot.refilling.swap(true, Ordering::AcqRel)
+            {
+                let n = self.worker_threads.len();
+                self.worker_threads[t.slot.id as usize % n].unpark();
+            }
         }
-        if underrun_any {
-            let (_, cv) = &*eng.thread_wait;
-            cv.notify_all();
+        if had_underrun {
+            self.underrun_callbacks.fetch_add(1, Ordering::Relaxed);
         }
+        if zeros > 0 {
+            self.underrun_samples.fetch_add(zeros, Ordering::Relaxed);
+        }
+        // 트랜스포트 진행 (콜백 도중 seek가 들어왔으면 seek 위치를 그대로 둠)
+        self.play_time_manager
+            .advance_from(start_pos, frames as u64);
+        frames
     }
-    if had_underrun {
-        eng.underrun_callbacks.fetch_add(1, Ordering::Relaxed);
-    }
-    if zeros > 0 {
-        eng.underrun_samples.fetch_add(zeros, Ordering::Relaxed);
-    }
-    // 트랜스포트 진행
-    eng.play_time_manager.advance_frames(frames as u64);
-    frames
+}
+
+// -------------------------
+// 링버퍼 채움 정도: (쌓인 frames, 용량 frames)
+// -------------------------
+#[inline]
+pub fn ring_fill_frames(prod: &Producer<f32>) -> (usize, usize) {
+    let cap = prod.buffer().capacity() / CHANNELS;
+    (cap - prod.slots() / CHANNELS, cap)
 }
 
 // -------------------------
@@ -260,6 +345,24 @@ fn push_silence(prod: &mut Producer<f32>, frames: usize) -> usize {
     wrote
 }
 
+// -------------------------
+// 링버퍼에 인터리브 프레임 채우기
+// -------------------------
+#[inline]
+fn push_frames(prod: &mut Producer<f32>, samples: &[f32]) -> usize {
+    let mut wrote = 0usize;
+    for fr in samples.chunks_exact(CHANNELS) {
+        if prod.slots() < CHANNELS {
+            break;
+        }
+        for &v in fr {
+            let _ = prod.push(v);
+        }
+        wrote += 1;
+    }
+    wrote
+}
+
 // -------------------------
 // 디코더에서 패킷 읽기
 // -------------------------
@@ -312,6 +415,35 @@ fn fetch_lr_once(
     }
 }
 
+// -------------------------
+// src_begin 까지 프레임 스킵 (false = 그 전에 EOF)
+// -------------------------
+fn skip_to(
+    d: &mut DecoderState,
+    si: &mut usize,
+    ch: &mut usize,
+    src_begin: u64,
+) -> Result<bool, String> {
+    while d.src_pos_samples < src_begin {
+        //샘플에 현재위치를 트랙에 현재위치까지 이동시켜 맞춤
+        let samples = d.sample_buf.samples(); //샘플 전체를 전달
+        if *si + *ch <= samples.len() {
+            //현재 위치 + 채널수가 샘플 길이보다 작으면
+            *si += *ch; //현재 위치를 채널수만큼 이동
+            d.src_pos_samples += 1; //재생위치도 한칸 이동
+        } else {
+            //예외처리
+            *ch = refill_packet(d)?; //다시 패키지 읽어온다
+            *si = 0; //다시 초기화
+            if d.sample_buf.samples().is_empty() {
+                //완전히 다출력했다면
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
 // -------------------------
 // 디코더 시킹
 // -------------------------
@@ -329,8 +461,8 @@ pub fn seek_decoder_to_src_samples(dec: &mut DecoderState, src_off: u64) -> anyh
         t.id //트랙 아이디
     };
 
-    // 2) 이제 mutable borrow로 seek 가능
-    dec.format.seek(
+    // 2) 이제 mutable borrow로 seek 가능 (실제 도착 위치는 목표보다 앞쪽 패킷 경계일 수 있음)
+    let seeked = dec.format.seek(
         SeekMode::Accurate,
         SeekTo::Time {
             time,
@@ -339,7 +471,8 @@ pub fn seek_decoder_to_src_samples(dec: &mut DecoderState, src_off: u64) -> anyh
     )?;
 
     // (선택) 디코더 내부상태 초기화: 없으면 무시돼요
-    let _ = dec.decoder.reset();
+    dec.decoder.reset();
+    dec.window.cursor = None; //샘플버퍼를 새로 채우므로 창은 더 못 이어 읽음
 
     // 3) seek이 끝났으니 다시 immutable borrow로 채널 정보만 읽기
     let chans = dec
@@ -352,46 +485,198 @@ pub fn seek_decoder_to_src_samples(dec: &mut DecoderState, src_off: u64) -> anyh
     let spec = SignalSpec::new(dec.src_sr, chans);
     dec.sample_buf = SampleBuffer::<f32>::new(0, spec);
 
-    // 5) 디코더의 진행 샘플 카운터를 목표 위치로 맞춤
-    dec.src_pos_samples = src_off;
+    // 5) 디코더의 진행 샘플 카운터를 실제 도착 위치로 맞춤 (목표까지 남은 건 decode 쪽에서 스킵)
+    dec.src_pos_samples = seeked.actual_ts.min(src_off);
 
     Ok(())
 }
 
 // -------------------------
-// 디코더 준비
+// 클립 보이스 (겹친 클립마다 디코더 하나)
 // -------------------------
-#[inline]
-fn ensure_decoder_for(dec: &mut Option<DecoderState>, clip: &Clip) -> bool {
-    match dec {
-        //디코더가 없으면 열고, 있으면 재사용/재열기
-        None => match open_decoder_for(&clip.file_path) {
-            //디코더 열기
-            Ok(d) => {
-                //성공
-                *dec = Some(d); //저장
-                true //성공
+impl TrackVoices {
+    pub fn new() -> Self {
+        Self {
+            voices: Vec::new(),
+            mix: Vec::new(),
+        }
+    }
+
+    // clip을 맡을 보이스: 같은 클립 → 다른 활성 클립이 안 쓰는 보이스(같은 파일 우선) → 새로 열기
+    fn claim(&mut self, clip: &Clip, active: &[ClipKey]) -> Option<usize> {
+        let free = |v: &ClipVoice| !active.iter().any(|k| k.1 == v.clip_id);
+        let same_file =
+            |v: &ClipVoice| v.dec.file_path == clip.file_path && v.dec.src_sr == clip.src_sr;
+        let found = self
+            .voices
+            .iter()
+            .position(|v| v.clip_id == clip.id)
+            .or_else(|| self.voices.iter().position(|v| free(v) && same_file(v)))
+            .or_else(|| self.voices.iter().position(free));
+
+        let i = match found {
+            Some(i) => {
+                if !same_file(&self.voices[i]) {
+                    //다른 파일이거나 샘플링레이트가 다르면 다시 열기
+                    self.voices[i].dec = open_decoder_for(&clip.file_path).ok()?;
+                }
+                i
             }
-            Err(_) => false, //실패
-        },
-        Some(d0) => {
-            //디코더가 이미 있으면
-            if d0.file_path != clip.file_path || d0.src_sr != clip.src_sr {
-                //다른 파일이거나 샘플링레이트가 다르면
-                match open_decoder_for(&clip.file_path) {
-                    //다시 열기
-                    Ok(nd) => {
-                        *dec = Some(nd); //저장
-                        true //성공
-                    }
-                    Err(_) => false, //실패
+            None => {
+                if self.voices.len() >= MAX_CLIP_VOICES {
+                    return None;
                 }
-            } else {
-                //같은 파일이면 재사용
-                true //성공
+                let dec = open_decoder_for(&clip.file_path).ok()?;
+                self.voices.push(ClipVoice {
+                    clip_id: clip.id,
+                    dec,
+                });
+                self.voices.len() - 1
             }
+        };
+        self.voices[i].clip_id = clip.id;
+        Some(i)
+    }
+}
+impl Default for TrackVoices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline]
+fn clip_end(clip: &Clip) -> u64 {
+    clip.tl_start.saturating_add(clip.tl_len)
+}
+
+impl Clip {
+    // 샘플레이트 변경: 타임라인 프레임 값만 새 레이트로 (소스 구간은 소스 레이트 기준이라 그대로)
+    pub(crate) fn rescale(&mut self, old_sr: u32, new_sr: u32) {
+        self.tl_start = rescale_frames(self.tl_start, old_sr, new_sr);
+        self.tl_len = rescale_frames(self.tl_len, old_sr, new_sr).max(1);
+        self.fade_in.frames = rescale_frames(self.fade_in.frames, old_sr, new_sr);
+        self.fade_out.frames = rescale_frames(self.fade_out.frames, old_sr, new_sr);
+        self.loop_len = rescale_frames(self.loop_len, old_sr, new_sr);
+        self.tl_offset = rescale_frames(self.tl_offset, old_sr, new_sr);
+    }
+
+    // 출력 1프레임당 소스 프레임 (읽는 속도 = 피치, 나아가는 속도 = 길이), tempo: 그 구간 템포 비율
+    // 둘이 같으면 그냥 리샘플, 다르면 타임 스트레치
+    pub fn src_rates(&self, src_sr: u32, engine_sr: u32, tempo: Rate) -> (Rate, Rate) {
+        let base = src_sr as f64 / engine_sr as f64;
+        let pitch = cents_to_ratio(self.pitch_cents) as f64;
+        let read = match self.tempo_mode {
+            TempoMode::Resample => tempo.scale(base * pitch),
+            TempoMode::Stretch => Rate::constant(base * pitch),
+        };
+        (read, tempo.scale(self.advance_scale(src_sr, engine_sr)))
+    }
+
+    // 정해 둔 소스 구간을 끝까지 쓰는 클립 길이 (반복 클립/파일 끝까지 쓰는 클립은 None)
+    pub(crate) fn source_tl_len(&self, engine_sr: u32, tempo: &TempoMap) -> Option<u64> {
+        if self.loop_len > 0 || self.src_end == 0 {
+            return None;
         }
+        let span = self.src_end.saturating_sub(self.src_start) as f64
+            / self.advance_scale(self.src_sr, engine_sr);
+        let front = self.tl_start as f64 - self.tl_offset as f64; //잘려 나간 앞부분까지 포함한 재생 시작
+        let play = tempo.frames_for_span(front, span).round().max(0.0) as u64;
+        Some(play.saturating_sub(self.tl_offset).max(1))
     }
+
+    // 템포 비율 적분 1당 소스 진행 프레임
+    fn advance_scale(&self, src_sr: u32, engine_sr: u32) -> f64 {
+        let base = src_sr as f64 / engine_sr as f64;
+        match self.pitch_mode {
+            PitchMode::Varispeed => base * cents_to_ratio(self.pitch_cents) as f64,
+            PitchMode::Preserve => base,
+        }
+    }
+
+    // 이번 바퀴(반복 안 하면 클립) 시작부터 pos까지 소스가 나아간 양 (rel = 바퀴 안 위치)
+    pub fn src_advanced(
+        &self,
+        src_sr: u32,
+        engine_sr: u32,
+        tempo: &TempoMap,
+        pos: u64,
+        rel: u64,
+    ) -> f64 {
+        tempo.span(pos, rel) * self.advance_scale(src_sr, engine_sr)
+    }
+
+    // 타임라인 pos → 클립 재생 위치 (분할로 잘린 앞부분 포함)
+    #[inline]
+    pub fn play_rel(&self, pos: u64) -> u64 {
+        pos - self.tl_start + self.tl_offset
+    }
+
+    // 클립 재생 위치 → 반복 한 바퀴 안 위치 (반복 횟수를 다 채웠으면 None)
+    pub fn loop_rel(&self, rel: u64) -> Option<u64> {
+        if self.loop_len == 0 {
+            return Some(rel);
+        }
+        if self.loop_count > 0 && rel / self.loop_len >= self.loop_count as u64 {
+            return None;
+        }
+        Some(rel % self.loop_len)
+    }
+
+    // pos 다음 반복 경계 (디코더가 거기서 처음으로 되감김)
+    fn next_loop_boundary(&self, pos: u64) -> u64 {
+        if self.loop_len == 0 {
+            return u64::MAX;
+        }
+        let rel = self.play_rel(pos);
+        pos + (self.loop_len - rel % self.loop_len)
+    }
+}
+
+// 겹친 구간 자동 크로스페이드 길이 (in, out)
+// 앞 클립 꼬리와 겹치면 페이드 인, 뒤 클립 머리와 겹치면 페이드 아웃
+// 다른 클립 안에 통째로 들어간 클립은 페이드 없이 위에 겹쳐 섞음
+fn overlap_fades(clips: &BTreeMap<ClipKey, Clip>, clip: &Clip) -> (u64, u64) {
+    let (start, end) = (clip.tl_start, clip_end(clip));
+    let mut fade_in = 0u64;
+    let mut fade_out = 0u64;
+    for c in clips.range(..(start, 0)).map(|(_, c)| c) {
+        let c_end = clip_end(c);
+        if c_end > start && c_end <= end {
+            fade_in = fade_in.max(c_end - start);
+        }
+    }
+    for c in clips.range((start + 1, 0)..(end, 0)).map(|(_, c)| c) {
+        if clip_end(c) >= end {
+            fade_out = fade_out.max(end - c.tl_start);
+        }
+    }
+    (fade_in, fade_out)
+}
+
+// 사용자 페이드 + 자동 크로스페이드(더 긴 쪽, 자동은 equal-power) + 클립 게인
+pub(crate) fn clip_envelope(
+    clips: &BTreeMap<ClipKey, Clip>,
+    clip: &Clip,
+    pos: u64,
+) -> ClipEnvelope {
+    let (auto_in, auto_out) = overlap_fades(clips, clip);
+    let pick = |user: Fade, auto: u64| {
+        if auto > user.frames {
+            Fade {
+                frames: auto,
+                curve: FadeCurve::EqualPower,
+            }
+        } else {
+            user
+        }
+    };
+    ClipEnvelope::new(
+        pick(clip.fade_in, auto_in),
+        pick(clip.fade_out, auto_out),
+        clip.tl_len,
+        pos - clip.tl_start,
+    )
+    .with_gain(clip.gain_db, clip.invert)
 }
 
 // -------------------------
@@ -399,133 +684,352 @@ fn ensure_decoder_for(dec: &mut Option<DecoderState>, clip: &Clip) -> bool {
 // -------------------------
 pub fn fill_track_once(
     tr: &mut TrackTimeline,
-    dec: &mut Option<DecoderState>,
+    voices: &mut TrackVoices,
     prod: &mut Producer<f32>,
-    mut frames_need: usize,
+    frames_need: usize,
     engine_sr: u32,
-    tempo_ratio: f32,
-    transport_pos: u64,
+    tempo: &TempoMap,
+    transport: Playhead,
 ) -> Result<usize, String> {
-    if frames_need == 0 || prod.is_full() {
+    // 믹스 버퍼를 통째로 밀어넣으므로 링버퍼 빈 공간까지만
+    let mut frames_need = frames_need.min(prod.slots() / CHANNELS);
+    if frames_need == 0 {
         //할 일이 없음
         return Ok(0);
     }
 
-    if tr.write_pos_frames < transport_pos {
+    if tr.write_pos_frames < transport.stream {
         //트랙의 쓰기 위치가 재생 위치보다 뒤에 있으면
-        tr.write_pos_frames = transport_pos; //재생 위치로 맞춤
+        tr.write_pos_frames = transport.stream; //재생 위치로 맞춤
     }
 
-    let mut pos = tr.write_pos_frames; //현재 쓰기 위치
+    let looped = transport.looped;
+    let mut stream = tr.write_pos_frames; //현재 쓰기 위치 (스트림 좌표)
     let mut produced_total = 0usize; //마지막에 사용량 저장을 위해
+    let mut mix = std::mem::take(&mut voices.mix);
 
     while frames_need > 0 {
-        //현재 pos에 활성 클립 찾기
-        let active_clip = tr
-            .clips
-            .range(..=pos) //<= 0부터 pos 까지 키값을 가진 클립들중에서
-            .next_back() // 그범위에 가장 뒤에있는거 (즉, pos에 가장 가까운 시작점)
-            .and_then(|(_, c)| {
-                //and_then 는 Option이 Some일때만 실행 _ 시작부분 , c 클립
-                let end = c.tl_start.saturating_add(c.tl_len); //클립의 끝 위치 시작시작 + 길이 = 끝(saturating_add 오버플로우 방지)
-                if pos < end {
-                    //pos가 클립의 끝보다 작으면(즉, 클립 구간 안에 있으면)
-                    Some(c) // Some(c) 반환
-                } else {
-                    None
-                } //pos가 클립의 끝보다 작으면(즉, 클립 구간 안에 있으면) Some(c) 반환 아니면 None
-            });
-
-        match active_clip {
-            None => {
-                //빈 구간 → 다음 클립 시작 전까지 무음
-                let next_start = tr
-                    .clips
-                    .range((pos + 1)..) //pos+1 부터 끝까지 키값을 가진 클립들중에서
-                    .next() // 다음 클립 시작 위치찾기
-                    .map(|(s, _)| *s) // 키값(시작 위치) 추출
-                    .unwrap_or(u64::MAX); // 없으면 무한대
-                let gap = if next_start == u64::MAX {
-                    frames_need // 무한대면 다 채움
-                } else {
-                    ((next_start.saturating_sub(pos)) as usize) // 다음 클립 시작 전까지
-                        .min(frames_need) // 필요한 프레임
-                };
-
-                let wrote = push_silence(prod, gap); //무음 채우기
-                if wrote == 0 {
-                    break;
-                } // 링버퍼 만땅
-                produced_total += wrote; //생산량 누적
-                pos += wrote as u64; // 진행 시킴
-                frames_need -= wrote; // 남은 필요량 감소
+        // pos에 걸친 클립들 (늦게 시작한 순으로 최대 MAX_CLIP_VOICES개)
+        // 구간은 활성 클립 하나가 끝나거나 한 바퀴 돌거나 새 클립이 시작하는 곳에서 끊음
+        let mut active = [(0u64, 0u64); MAX_CLIP_VOICES];
+        let mut n_active = 0usize;
+        let pos = looped.fold(stream); //타임라인 위치
+                                       // 반복 구간 끝에서 끊고, 다음 구간은 구간 처음부터 이어서 씀
+        let mut seg_end = pos + (frames_need as u64).min(looped.until_wrap(stream));
+        for (key, c) in tr.clips.range(..=(pos, u64::MAX)).rev() {
+            let end = clip_end(c);
+            if pos < end && n_active < MAX_CLIP_VOICES {
+                active[n_active] = *key;
+                n_active += 1;
+                seg_end = seg_end.min(end).min(c.next_loop_boundary(pos));
             }
-            Some(clip) => {
-                // 클립 구간 → 필요한 만큼만 디코드 후 리샘플해서 push
-                let clip_end = clip.tl_start.saturating_add(clip.tl_len);
-                let can_write = ((clip_end.saturating_sub(pos)) as usize).min(frames_need);
-
-                // 디코더 열기/재열기
-                if !ensure_decoder_for(dec, clip) {
-                    // 디코더를 못 열면 'can_write' 만큼 무음으로 채우고 다음 루프로 (스핀 방지)
-                    let wrote = push_silence(prod, can_write.min(frames_need));
-                    produced_total += wrote; //생산량 누적
-                    pos += wrote as u64; // 진행 시킴
-                    frames_need = frames_need.saturating_sub(wrote); // 남은 필요량 감소
-                    continue; //다음 루프
-                }
-
-                // 여기부터는 안전
-                let d = dec.as_mut().unwrap();
-
-                // 타임라인 pos → 소스 좌표(src_sr)로 매핑
-                let rel = (pos.saturating_sub(clip.tl_start)) as f64; //클립내 상대 위치
-                let step = (d.src_sr as f64 / engine_sr as f64) * (tempo_ratio as f64); //디코더에서 덜읽어야할 sr 수치
-                let src_begin = (rel * step).floor() as u64; //디코더 기준 시작 위치
-
-                // 정확 시킹(필요 시)
-                if d.src_pos_samples != src_begin {
-                    if let Err(_) = seek_decoder_to_src_samples(d, src_begin) {
-                        // 실패: 디코더 폐기 + 무음으로 메우고 다음 루프
-                        *dec = None; //디코더 폐기
-                        let wrote = push_silence(prod, can_write.min(frames_need)); //무음으로 채우기
-                        produced_total += wrote; //생산량 누적
-                        pos += wrote as u64; // 진행 시킴
-                        frames_need = frames_need.saturating_sub(wrote); // 남은 필요량 감소
-                        continue; //다음 루프
-                    }
-                }
+        }
+        if let Some(((next_start, _), _)) = tr.clips.range((pos + 1, 0)..).next() {
+            seg_end = seg_end.min(*next_start); // 다음 클립 시작 전까지
+        }
+        seg_end = seg_end.min(tempo.next_change(pos)); // 템포 이벤트에서도 끊어 구간 안 속도를 직선으로
+        let active = &active[..n_active];
+        let frames = (seg_end - pos) as usize;
+
+        if active.is_empty() {
+            //빈 구간 → 무음
+            let wrote = push_silence(prod, frames); //무음 채우기
+            if wrote == 0 {
+                break;
+            } // 링버퍼 만땅
+            produced_total += wrote; //생산량 누적
+            stream += wrote as u64; // 진행 시킴
+            frames_need -= wrote; // 남은 필요량 감소
+            continue;
+        }
 
-                // 디코드/리샘플
-                // 위에서 정확 시킹을 했으므로, decode 쪽에서 추가 스킵 없게 src_begin=0 전달
-                match decode_resample_into_ring(d, can_write, engine_sr, prod, 0, tempo_ratio) {
-                    Ok(wrote) if wrote > 0 => {
-                        produced_total += wrote;
-                        pos += wrote as u64;
-                        frames_need -= wrote;
-                    }
-                    _ => {
-                        // EOF/에러/포화 → 무음으로 진행 유지
-                        *dec = None;
-                        let wrote = push_silence(prod, can_write.min(frames_need));
-                        produced_total += wrote;
-                        pos += wrote as u64;
-                        frames_need = frames_need.saturating_sub(wrote);
-                        continue;
-                    }
-                }
+        // 클립 구간 → 클립마다 디코드/리샘플해서 믹스 버퍼에 더한 뒤 push
+        mix.clear();
+        mix.resize(slots(frames), 0.0);
+        for key in active {
+            let clip = &tr.clips[key];
+            // 디코더를 못 열면 이 클립은 무음
+            let Some(i) = voices.claim(clip, active) else {
+                continue;
+            };
+            let env = clip_envelope(&tr.clips, clip, pos);
+            let d = &mut voices.voices[i].dec;
+            if !mix_clip_into(d, clip, env, &mut mix, pos, engine_sr, tempo) {
+                // EOF/에러 → 디코더 폐기, 남은 부분은 무음
+                voices.voices.swap_remove(i);
             }
         }
 
-        if prod.is_full() {
+        let wrote = push_frames(prod, &mix);
+        produced_total += wrote; //생산량 누적
+        stream += wrote as u64; // 진행 시킴
+        frames_need -= wrote; // 남은 필요량 감소
+        if wrote < frames {
             break;
         } //링버퍼 꽉 참
     }
 
-    tr.write_pos_frames = pos; //트랙의 '공식' 쓰기 위치를 갱신
+    voices.mix = mix;
+    tr.write_pos_frames = stream; //트랙의 '공식' 쓰기 위치를 갱신
     Ok(produced_total) //종료
 }
 
+// 클립 하나를 pos부터 out 길이만큼 디코드해서 out에 더함 (false = 디코더 못 씀)
+fn mix_clip_into(
+    d: &mut DecoderState,
+    clip: &Clip,
+    mut env: ClipEnvelope,
+    out: &mut [f32],
+    pos: u64,
+    engine_sr: u32,
+    tempo: &TempoMap,
+) -> bool {
+    // 타임라인 pos → 소스 좌표(src_sr)로 매핑 (반복 횟수를 다 채웠으면 나머지는 무음)
+    let Some(rel) = clip.loop_rel(clip.play_rel(pos)) else {
+        return true;
+    };
+    let (read, step) = clip.src_rates(d.src_sr, engine_sr, tempo.rate_at(pos)); //디코더에서 덜읽어야할 sr 수치
+    let done = clip.src_advanced(d.src_sr, engine_sr, tempo, pos, rel); //반복 한 바퀴 안에서 나아간 소스 프레임
+    if read != step {
+        // 역재생이면 소스 좌표는 구간 끝에서부터 거꾸로 잰 거리 (0부터)
+        let (base, src0) = if clip.reversed {
+            (0, done)
+        } else {
+            (clip.src_start, clip.src_start as f64 + done)
+        };
+        let p = StretchParams {
+            clip_id: clip.id,
+            base,
+            rel0: rel,
+            src0,
+            read,
+            speed: step,
+            reversed: clip.reversed,
+        };
+        return mix_stretched_into(d, clip, &mut env, out, &p);
+    }
+    if clip.reversed {
+        return mix_reversed_into(d, clip, &mut env, out, done, step);
+    }
+    let src_begin = clip.src_start + done.floor() as u64; //디코더 기준 시작 위치 (소스 오프셋 포함)
+
+    // 소스 끝(src_end)을 지나면 클립 나머지는 무음, 아니면 src_end까지만 디코드
+    let mut frames = out.len() / CHANNELS;
+    if clip.src_end > 0 {
+        if src_begin >= clip.src_end {
+            return true;
+        }
+        let src_left = (clip.src_end - src_begin) as f64;
+        let need = step.frames_for(src_left).ceil().min(frames as f64) as usize;
+        frames = frames.min(need.max(1));
+    }
+
+    // 정확 시킹(필요 시)
+    if d.src_pos_samples != src_begin && seek_decoder_to_src_samples(d, src_begin).is_err() {
+        return false;
+    }
+
+    // 디코드/리샘플
+    // 시킹은 패킷 경계까지만 가므로 src_begin까지 나머지는 decode 쪽에서 스킵
+    matches!(
+        decode_resample_into(d, &mut out[..slots(frames)], src_begin, step, &mut env),
+        Ok(wrote) if wrote > 0
+    )
+}
+
+// 역재생: 필요한 소스 블록을 앞으로 디코드해 두고 끝에서부터 거꾸로 읽으며 리샘플
+// 소스 구간은 src_start..src_end (src_end == 0 이면 파일 끝, 길이를 모르면 무음)
+fn mix_reversed_into(
+    d: &mut DecoderState,
+    clip: &Clip,
+    env: &mut ClipEnvelope,
+    out: &mut [f32],
+    done: f64,
+    step: Rate,
+) -> bool {
+    let src_stop = if clip.src_end > 0 {
+        clip.src_end
+    } else {
+        d.src_len
+    };
+    if src_stop <= clip.src_start {
+        return true;
+    }
+    let top = (src_stop - 1) as f64; //역재생 첫 프레임 (소스 구간 마지막)
+    let bottom = clip.src_start as f64;
+    let src_at = |k: usize| top - (done + step.advance(k as f64)); //출력 k번째 → 소스 위치
+
+    let frames = out.len() / CHANNELS;
+    let mut k0 = 0usize;
+    while k0 < frames {
+        let n = (frames - k0).min(CHUNK_REVERSE);
+        let hi = src_at(k0);
+        if hi < bottom {
+            break; // 소스 시작까지 다 돌았으면 나머지는 무음
+        }
+        let lo = src_at(k0 + n - 1).max(bottom);
+        let from = lo.floor() as u64;
+        let to = (hi.floor() as u64 + 2).min(src_stop); //보간용 한 프레임 더
+        // 창 밑으로 내려가면 그 아래를 넉넉히 한 번에 디코드해 둠
+        let start = to
+            .saturating_sub(REVERSE_WINDOW as u64)
+            .max(clip.src_start)
+            .min(from);
+        if fill_window(d, from, to, start).is_err() {
+            return false;
+        }
+        let w = &d.window;
+        let got = w.buf.len() / CHANNELS;
+
+        for k in k0..k0 + n {
+            let g = env.next_gain();
+            let p = src_at(k);
+            if p < bottom {
+                continue;
+            }
+            let Some(i) = (p.floor() as u64).checked_sub(w.from) else {
+                continue;
+            };
+            let i = i as usize;
+            if i >= got {
+                continue;
+            }
+            let frac = (p - p.floor()) as f32;
+            let (l0, r0) = (w.buf[i * 2], w.buf[i * 2 + 1]);
+            let (l1, r1) = if i + 1 < got {
+                (w.buf[i * 2 + 2], w.buf[i * 2 + 3])
+            } else {
+                (l0, r0)
+            };
+            out[k * 2] += (l0 + (l1 - l0) * frac).clamp(-1.0, 1.0) * g;
+            out[k * 2 + 1] += (r0 + (r1 - r0) * frac).clamp(-1.0, 1.0) * g;
+        }
+        k0 += n;
+    }
+    true
+}
+
+// 타임 스트레치: 필요한 소스 구간을 창에 이어 붙여 두고 grain은 read 속도(피치)로 읽고
+// 위치는 advance 속도(길이)로 나아가게 해서 템포/피치를 따로 바꿈
+// 역재생 클립은 p가 구간 끝(top)에서부터 거꾸로 잰 좌표라서 창도 아래로 내려가며 채움
+fn mix_stretched_into(
+    d: &mut DecoderState,
+    clip: &Clip,
+    env: &mut ClipEnvelope,
+    out: &mut [f32],
+    p: &StretchParams,
+) -> bool {
+    let src_stop = if clip.src_end > 0 {
+        clip.src_end
+    } else if d.src_len > 0 {
+        d.src_len
+    } else if clip.reversed {
+        return true; // 길이를 모르면 역재생은 무음
+    } else {
+        u64::MAX
+    };
+    if p.reversed && src_stop <= clip.src_start {
+        return true;
+    }
+    let top = src_stop - 1; //역재생 첫 프레임 (소스 구간 마지막)
+
+    let frames = out.len() / CHANNELS;
+    let mut k0 = 0usize;
+    while k0 < frames {
+        let n = (frames - k0).min(CHUNK_STRETCH);
+        let at = p.rel0 + k0 as u64;
+        let (lo, hi) = Stretcher::source_range(p, at, n);
+        let (lo, hi, start) = if p.reversed {
+            let (lo, hi) = (
+                (top + 1).saturating_sub(hi).max(clip.src_start),
+                (top + 1).saturating_sub(lo),
+            );
+            let start = hi
+                .saturating_sub(REVERSE_WINDOW as u64)
+                .max(clip.src_start)
+                .min(lo);
+            (lo, hi, start)
+        } else {
+            (lo, hi.min(src_stop), lo)
+        };
+        // 소스 구간을 벗어난 부분은 빈 블록 (앞 grain 꼬리만 남음)
+        let src = if lo < hi {
+            if fill_window(d, lo, hi, start).is_err() {
+                return false;
+            }
+            let w = &d.window;
+            let got = (w.buf.len() / CHANNELS).min(src_stop.saturating_sub(w.from) as usize);
+            if p.reversed {
+                // 구간 시작 아래는 무음이어야 하므로 잘라냄
+                let skip = (clip.src_start.saturating_sub(w.from) as usize).min(got);
+                StretchSource::reversed(&w.buf[slots(skip)..slots(got)], w.from + skip as u64, top)
+            } else {
+                StretchSource::new(&w.buf[..slots(got)], w.from)
+            }
+        } else {
+            StretchSource::new(&[], lo)
+        };
+        d.stretch
+            .render(&src, p, at, &mut out[slots(k0)..slots(k0 + n)], env);
+        k0 += n;
+    }
+    true
+}
+
+// 창이 [lo, hi)를 담게 함 (EOF면 거기까지)
+// 창 안이면 그대로, 창 끝에 이어지면 앞으로 더 읽고, 아니면 start(<= lo)로 시킹해서 새로 채움
+fn fill_window(d: &mut DecoderState, lo: u64, hi: u64, start: u64) -> Result<(), String> {
+    let w = &d.window;
+    let end = w.from + (w.buf.len() / CHANNELS) as u64;
+    if lo >= w.from && (hi <= end || (w.eof && lo <= end)) {
+        return Ok(());
+    }
+    let (mut si, mut ch) = match w.cursor {
+        Some(cursor) if lo >= w.from && lo <= end && d.src_pos_samples == end => {
+            // 앞으로만 나아가므로 lo 앞은 버림
+            let drop = (lo - w.from) as usize;
+            d.window.buf.drain(..slots(drop));
+            d.window.from = lo;
+            cursor
+        }
+        _ => {
+            seek_decoder_to_src_samples(d, start).map_err(|e| e.to_string())?;
+            d.window.buf.clear();
+            d.window.from = start;
+            d.window.eof = false;
+            let mut ch = refill_packet(d)?;
+            let mut si = 0usize;
+            if ch == 0 || !skip_to(d, &mut si, &mut ch, start)? {
+                d.window.eof = true;
+                d.window.cursor = None;
+                return Ok(());
+            }
+            (si, ch)
+        }
+    };
+    // fetch_lr_once가 디코더 전체를 빌리므로 창 버퍼는 잠깐 떼어 내서 채움
+    let mut buf = std::mem::take(&mut d.window.buf);
+    let want = slots((hi - d.window.from) as usize);
+    let mut eof = false;
+    while buf.len() < want {
+        match fetch_lr_once(d, &mut si, &mut ch)? {
+            Some((l, r)) => {
+                buf.push(l);
+                buf.push(r);
+            }
+            None => {
+                eof = true;
+                break;
+            }
+        }
+    }
+    let w = &mut d.window;
+    w.buf = buf;
+    w.eof = eof;
+    w.cursor = (!eof).then_some((si, ch));
+    Ok(())
+}
+
 // -------------------------
 // 디코더 열기
 // -------------------------
@@ -558,6 +1062,7 @@ fn open_decoder_for(path: &str) -> Result<DecoderState, String> {
         .codec_params
         .sample_rate
         .ok_or_else(|| "unknown sample rate".to_string())?; //샘플링 레이트 정보
+    let src_len = track.codec_params.n_frames.unwrap_or(0); //전체 길이 (모르면 0, 역재생에 필요)
 
     let decoder = get_codecs()
         .make(&track.codec_params, &DecoderOptions::default())
@@ -576,25 +1081,27 @@ fn open_decoder_for(path: &str) -> Result<DecoderState, String> {
         sample_buf,
         src_sr,
         src_pos_samples: 0,
+        src_len,
         file_path: path.to_string(),
+        window: SourceWindow::default(),
+        stretch: Stretcher::new(),
     })
 }
 
 //-------------------------
-// 디코드 + 리샘플링 + 링버퍼 푸시
+// 디코드 + 리샘플링 + 믹스 버퍼에 더하기
 //-------------------------
-fn decode_resample_into_ring(
+fn decode_resample_into(
     d: &mut DecoderState,
-    out_frames: usize,
-    engine_sr: u32,
-    prod: &mut Producer<f32>,
+    out: &mut [f32],
     src_begin: u64,
-    tempo_ratio: f32,
+    step: Rate,
+    env: &mut ClipEnvelope,
 ) -> Result<usize, String> {
+    let out_frames = out.len() / CHANNELS;
+    d.window.cursor = None; // 디코더를 직접 돌리므로 창 이어 읽기는 끊김
     let mut wrote = 0usize; //실제로 쓴 프레임 수
-    let mut step = (d.src_sr as f32) / (engine_sr as f32); //디코더에서 덜읽어야할 sr 수치
-    step *= tempo_ratio.clamp(0.25, 4.0); //템포 비율 적용 (너무 극단적인 값은 방지)
-                                          // 로컬 커서
+                            // 로컬 커서
     let mut ch: usize = refill_packet(d)?; // 첫 패킷 적재 & 채널수 확보
     if ch == 0 {
         //채널이 0이면
@@ -603,22 +1110,8 @@ fn decode_resample_into_ring(
     let mut si: usize = 0; //샘플버퍼내 현재 위치
 
     // A) src_begin 까지 프레임 스킵(정확 시킹 대체)
-    while d.src_pos_samples < src_begin {
-        //샘플에 현재위치를 트랙에 현재위치까지 이동시켜 맞춤
-        let samples = d.sample_buf.samples(); //샘플 전체를 전달
-        if si + ch <= samples.len() {
-            //현재 위치 + 채널수가 샘플 길이보다 작으면
-            si += ch; //현재 위치를 채널수만큼 이동
-            d.src_pos_samples += 1; //재생위치도 한칸 이동
-        } else {
-            //예외처리
-            ch = refill_packet(d)?; //다시 패키지 읽어온다
-            si = 0; //다시 초기화
-            if d.sample_buf.samples().is_empty() {
-                //완전히 다출력했다면
-                return Ok(0); //EOF
-            }
-        }
+    if !skip_to(d, &mut si, &mut ch, src_begin)? {
+        return Ok(0); //EOF
     }
 
     // B) 선형보간 준비
@@ -641,17 +1134,15 @@ fn decode_resample_into_ring(
         let out_r = (s0.1 + (s1.1 - s0.1) * frac).clamp(-1.0, 1.0); // 선형보간 계산법 A + (B - A) *frac
         let out_l = if out_l.is_finite() { out_l } else { 0.0 }; //무한대나 NaN 방지
         let out_r = if out_r.is_finite() { out_r } else { 0.0 }; // 무한대나 NaN 방지
+        let g = env.next_gain(); //페이드 인/아웃
+        let (out_l, out_r) = (out_l * g, out_r * g);
 
-        if prod.push(out_l).is_err() {
-            // L
-            break;
-        } //링버퍼에 넣기 에러나면 종료
-        if prod.push(out_r).is_err() {
-            break;
-        }
-        wrote += 1; // R
+        out[wrote * 2] += out_l; // L
+        out[wrote * 2 + 1] += out_r; // R
+        wrote += 1;
 
-        frac += step; //step 읽어 나가야할 값
+        // 출력 1프레임당 소스 진행 (샘플레이트 비율 × 템포 × 피치), 램프면 프레임 가운데 속도로 적분과 맞춤
+        frac += step.step(wrote as f64 - 0.5) as f32;
         while frac >= 1.0 {
             // 1초 보다 크면
             frac -= 1.0; // 1초 보다 작게 만들고
@@ -667,3 +1158,100 @@ fn decode_resample_into_ring(
 
     Ok(wrote) //실제로 쓴 프레임 수 반환
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LoopRegion;
+    use rtrb::RingBuffer;
+
+    const SR: u32 = 48_000;
+    const SPLIT: u32 = 32_000; // 프레임 번호 = R * SPLIT + L (i16 하나에 다 안 들어가서)
+
+    // 프레임마다 자기 번호를 담은 48k 스테레오 wav
+    fn ramp_wav(name: &str, frames: u32) -> String {
+        let file = format!("ryuichi_{}_{name}.wav", std::process::id());
+        let path = std::env::temp_dir().join(file);
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: SR,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut w = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..frames {
+            w.write_sample((i % SPLIT) as i16).unwrap();
+            w.write_sample((i / SPLIT) as i16).unwrap();
+        }
+        w.finalize().unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    // 클립 하나짜리 트랙을 처음부터 block씩 frames만큼 렌더해서 소스 프레임 번호로 되돌림
+    fn render(clip: Clip, frames: usize, block: usize) -> Vec<u64> {
+        let mut tr = TrackTimeline {
+            clips: BTreeMap::from([((clip.tl_start, clip.id), clip)]),
+            write_pos_frames: 0,
+        };
+        let mut v = TrackVoices::new();
+        let (mut prod, mut cons) = RingBuffer::<f32>::new(slots(frames));
+        let tempo = TempoMap::new(BASE_BPM, Vec::new());
+        let mut done = 0;
+        while done < frames {
+            let n = block.min(frames - done);
+            let at = Playhead {
+                stream: done as u64,
+                looped: LoopRegion::NONE,
+            };
+            let got = fill_track_once(&mut tr, &mut v, &mut prod, n, SR, &tempo, at);
+            assert_eq!(got, Ok(n));
+            done += n;
+        }
+        let frame = |s: f32| (s * 32_768.0).round() as u64;
+        let samples: Vec<f32> = std::iter::from_fn(|| cons.pop().ok()).collect();
+        samples
+            .chunks(CHANNELS)
+            .map(|lr| frame(lr[1]) * SPLIT as u64 + frame(lr[0]))
+            .collect()
+    }
+
+    #[test]
+    fn clip_loop_wraps_back_to_source_start() {
+        let path = ramp_wav("loop", 4_000);
+        let (src_start, loop_len) = (100u64, 1_000u64);
+        // tl_offset: 분할된 뒷조각처럼 반복 중간에서 시작해도 경계가 같은 자리
+        for tl_offset in [0, 600] {
+            let mut clip = Clip::new(1, path.clone(), SR, 0, 3_500, src_start, 0);
+            clip.loop_len = loop_len;
+            clip.tl_offset = tl_offset;
+            let got = render(clip, 3_500, 3_500);
+            for (k, &f) in got.iter().enumerate() {
+                let want = src_start + (k as u64 + tl_offset) % loop_len;
+                assert_eq!(f, want, "offset {tl_offset}, frame {k}");
+            }
+        }
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn reversed_read_is_in_order_across_window_edges() {
+        // 창(REVERSE_WINDOW) 두 개를 넘는 구간: 창 아래로 내려갈 때마다 새로 시킹
+        let src_end = 2 * REVERSE_WINDOW as u64 + 123;
+        let path = ramp_wav("reverse", src_end as u32 + 500);
+        let src_start = 50u64;
+        let span = (src_end - src_start) as usize;
+        // 한 번에 / 청크(CHUNK_REVERSE)에 안 맞는 블록으로 나눠서
+        let len = span + 100;
+        for block in [len, 1_000] {
+            let mut clip = Clip::new(1, path.clone(), SR, 0, len as u64, src_start, src_end);
+            clip.reversed = true;
+            let got = render(clip, len, block);
+            for (k, &f) in got[..span].iter().enumerate() {
+                assert_eq!(f, src_end - 1 - k as u64, "block {block}, frame {k}");
+            }
+            // 소스 시작까지 다 돌면 나머지는 무음
+            assert!(got[span..].iter().all(|&f| f == 0), "block {block}");
+        }
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,500 @@
+use crate::unit::*;
+use crate::ClipEnvelope;
+use crate::Rate;
+use std::f64::consts::TAU;
+
+// -------------------------
+// 템포 변화 처리 방식 (클립마다)
+// -------------------------
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TempoMode {
+    #[default]
+    Resample, // 재생 속도를 바꿈 (템포와 함께 피치도 변함)
+    Stretch, // WSOLA 타임 스트레치 (피치 유지)
+}
+
+impl TempoMode {
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(Self::Resample),
+            1 => Some(Self::Stretch),
+            _ => None,
+        }
+    }
+    pub fn code(self) -> u32 {
+        match self {
+            Self::Resample => 0,
+            Self::Stretch => 1,
+        }
+    }
+}
+
+// -------------------------
+// 클립 피치를 바꾸는 방식
+// -------------------------
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PitchMode {
+    #[default]
+    Varispeed, // 빠르게/느리게 읽음 (피치와 함께 길이도 변함)
+    Preserve, // 길이는 그대로 피치만 (스트레치로 보정)
+}
+
+impl PitchMode {
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(Self::Varispeed),
+            1 => Some(Self::Preserve),
+            _ => None,
+        }
+    }
+    pub fn code(self) -> u32 {
+        match self {
+            Self::Varispeed => 0,
+            Self::Preserve => 1,
+        }
+    }
+}
+
+const HOP: usize = STRETCH_GRAIN / 2; // 50% 겹침 (Hann 창 합이 정확히 1)
+
+// 디코드해 둔 소스 블록 (from부터 L/R, 밖은 무음)
+pub struct StretchSource<'a> {
+    buf: &'a [f32],
+    from: u64,
+    top: Option<u64>, //역재생이면 Some(top): 위치 i는 소스 top - i
+}
+impl<'a> StretchSource<'a> {
+    pub fn new(buf: &'a [f32], from: u64) -> Self {
+        Self {
+            buf,
+            from,
+            top: None,
+        }
+    }
+
+    pub fn reversed(buf: &'a [f32], from: u64, top: u64) -> Self {
+        Self {
+            buf,
+            from,
+            top: Some(top),
+        }
+    }
+
+    #[inline]
+    fn frame(&self, i: i64) -> (f32, f32) {
+        let i = match self.top {
+            Some(top) => top as i64 - i,
+            None => i,
+        };
+        let i = i - self.from as i64;
+        if i < 0 || (i as usize) * CHANNELS + 1 >= self.buf.len() {
+            return (0.0, 0.0);
+        }
+        let i = i as usize * CHANNELS;
+        (self.buf[i], self.buf[i + 1])
+    }
+
+    // 소스 위치 p (소수) 선형보간
+    #[inline]
+    fn at(&self, p: f64) -> (f32, f32) {
+        let i = p.floor();
+        let frac = (p - i) as f32;
+        let (l0, r0) = self.frame(i as i64);
+        let (l1, r1) = self.frame(i as i64 + 1);
+        (
+            (l0 + (l1 - l0) * frac).clamp(-1.0, 1.0),
+            (r0 + (r1 - r0) * frac).clamp(-1.0, 1.0),
+        )
+    }
+
+    #[inline]
+    fn mono(&self, p: f64) -> f32 {
+        let (l, r) = self.frame(p.floor() as i64);
+        (l + r) * 0.5
+    }
+}
+
+// 클립 하나를 타임 스트레치할 때의 좌표 (출력 rel0 = 소스 src0, 속도는 rel0부터 출력 프레임 기준)
+#[derive(Clone, Copy)]
+pub struct StretchParams {
+    pub clip_id: u64,
+    pub base: u64,      //소스 시작 위치 (src_start), 이 앞은 읽지 않음
+    pub rel0: u64,      //이번 구간 첫 출력 위치 (클립 재생 위치 기준)
+    pub src0: f64,      //rel0에서의 소스 위치
+    pub read: Rate,     //grain 안에서 출력 1프레임당 읽는 소스 프레임 (피치)
+    pub speed: Rate,    //출력 1프레임당 소스 진행 (길이)
+    pub reversed: bool, //역재생 (위치는 소스 구간 끝에서부터 거꾸로 잰 거리)
+}
+impl StretchParams {
+    #[inline]
+    fn offset(&self, k: u64) -> f64 {
+        (k * HOP as u64) as f64 - self.rel0 as f64
+    }
+
+    // grain k가 원래 놓일 소스 위치
+    #[inline]
+    fn nominal(&self, k: u64) -> f64 {
+        self.src0 + self.speed.advance(self.offset(k))
+    }
+
+    // grain k를 읽는 속도
+    #[inline]
+    fn read_step(&self, k: u64) -> f64 {
+        self.read.step(self.offset(k)).max(0.0)
+    }
+}
+
+// -------------------------
+// WSOLA: 원래 피치로 읽은 grain을 HOP 간격으로 겹쳐 더하고,
+// grain 위치는 템포대로 나아가되 앞 grain이 자연스럽게 이어질 자리와 가장 닮은 곳으로 살짝 옮김
+// 호출 사이에 상태를 들고 있어서 출력 위치가 이어지면 끊김 없이 계속, 아니면 그 자리에서 다시 시작
+// -------------------------
+pub struct Stretcher {
+    key: Option<(u64, u64, bool)>, //(클립 id, base, 역재생) — 바뀌면 처음부터 (속도는 구간마다 이어서 바뀜)
+    next_out: u64,                 //다음에 낼 출력 위치 (클립 재생 위치 기준)
+    next_grain: u64,               //다음에 더할 grain 번호
+    prev: Option<(f64, f64)>,      //바로 앞 grain의 실제 소스 위치, 읽은 속도
+    acc: Vec<f32>,                 //앞 grain의 뒷절반 (다음 블록에 더해짐)
+    block: Vec<f32>,               //완성된 블록 (next_grain - 1 번, HOP frames)
+    grain: Vec<f32>,
+    window: Vec<f32>,
+    tmpl: Vec<f32>,
+}
+
+impl Stretcher {
+    pub fn new() -> Self {
+        let window = (0..STRETCH_GRAIN)
+            .map(|j| (0.5 - 0.5 * (TAU * j as f64 / STRETCH_GRAIN as f64).cos()) as f32)
+            .collect();
+        Self {
+            key: None,
+            next_out: 0,
+            next_grain: 0,
+            prev: None,
+            acc: vec![0.0; slots(HOP)],
+            block: vec![0.0; slots(HOP)],
+            grain: vec![0.0; slots(STRETCH_GRAIN)],
+            window,
+            tmpl: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.key = None;
+    }
+
+    // 출력 rel..rel+frames 를 만들 때 읽는 소스 구간 [lo, hi)
+    pub fn source_range(p: &StretchParams, rel: u64, frames: usize) -> (u64, u64) {
+        let first = (rel / HOP as u64).saturating_sub(1);
+        let last = (rel + frames as u64) / HOP as u64 + 1;
+        let read = p.read_step(first).max(p.read_step(last));
+        let lo = p.nominal(first) - STRETCH_SEEK as f64;
+        let hi = p.nominal(last) + STRETCH_SEEK as f64 + STRETCH_GRAIN as f64 * read + 2.0;
+        ((lo.floor().max(p.base as f64)) as u64, hi.ceil() as u64)
+    }
+
+    // 출력 rel부터 out 길이만큼 더함 (src는 source_range 구간을 담고 있어야 함)
+    pub fn render(
+        &mut self,
+        src: &StretchSource,
+        p: &StretchParams,
+        rel: u64,
+        out: &mut [f32],
+        env: &mut ClipEnvelope,
+    ) {
+        let key = (p.clip_id, p.base, p.reversed);
+        if self.key != Some(key) || self.next_out != rel {
+            self.restart(src, p, rel);
+            self.key = Some(key);
+        }
+        let frames = out.len() / CHANNELS;
+        let mut k = 0usize;
+        while k < frames {
+            let b = self.next_out / HOP as u64;
+            if self.next_grain != b + 1 {
+                self.add_grain(src, p, b); // 블록 b는 grain b를 더해야 완성
+            }
+            let off = (self.next_out - b * HOP as u64) as usize;
+            let n = (HOP - off).min(frames - k);
+            for i in 0..n {
+                let g = env.next_gain();
+                out[(k + i) * 2] += self.block[(off + i) * 2] * g;
+                out[(k + i) * 2 + 1] += self.block[(off + i) * 2 + 1] * g;
+            }
+            k += n;
+            self.next_out += n as u64;
+        }
+    }
+
+    // rel이 든 블록부터 다시: 앞 grain은 원래 위치에 놓고 뒷절반만 남김
+    fn restart(&mut self, src: &StretchSource, p: &StretchParams, rel: u64) {
+        let b = rel / HOP as u64;
+        self.acc.fill(0.0);
+        self.prev = None;
+        if b > 0 {
+            let (at, step) = (p.nominal(b - 1), p.read_step(b - 1));
+            self.read_grain(src, at, step, false);
+            self.acc.copy_from_slice(&self.grain[slots(HOP)..]);
+            self.prev = Some((at, step));
+        }
+        self.next_grain = b;
+        self.next_out = rel;
+    }
+
+    fn add_grain(&mut self, src: &StretchSource, p: &StretchParams, k: u64) {
+        let nominal = p.nominal(k);
+        let step = p.read_step(k);
+        let at = match self.prev {
+            Some((prev, prev_step)) => {
+                self.best_position(src, prev + HOP as f64 * prev_step, nominal, step)
+            }
+            None => nominal,
+        }
+        .max(p.base as f64);
+        self.read_grain(src, at, step, k == 0);
+        for i in 0..slots(HOP) {
+            self.block[i] = self.acc[i] + self.grain[i];
+        }
+        self.acc.copy_from_slice(&self.grain[slots(HOP)..]);
+        self.prev = Some((at, step));
+        self.next_grain = k + 1;
+    }
+
+    // 창을 씌운 grain 하나 (첫 grain은 앞에 겹칠 게 없으므로 앞절반 창 없이)
+    fn read_grain(&mut self, src: &StretchSource, at: f64, step: f64, first: bool) {
+        for j in 0..STRETCH_GRAIN {
+            let w = if first && j < HOP {
+                1.0
+            } else {
+                self.window[j]
+            };
+            let (l, r) = src.at(at + j as f64 * step);
+            self.grain[j * 2] = l * w;
+            self.grain[j * 2 + 1] = r * w;
+        }
+    }
+
+    // nominal ± STRETCH_SEEK 안에서 겹치는 구간이 target(앞 grain의 자연스러운 다음 자리)과 가장 닮은 위치
+    fn best_position(&mut self, src: &StretchSource, target: f64, nominal: f64, step: f64) -> f64 {
+        let n = HOP / STRETCH_CORR_STRIDE;
+        let stride = STRETCH_CORR_STRIDE as f64 * step;
+        self.tmpl.clear();
+        self.tmpl
+            .extend((0..n).map(|j| src.mono(target + j as f64 * stride)));
+        if self.tmpl.iter().all(|&x| x == 0.0) {
+            return nominal; // 무음이면 찾을 게 없음
+        }
+
+        let score = |at: f64| {
+            let (mut num, mut energy) = (0.0f32, 0.0f32);
+            for (j, &t) in self.tmpl.iter().enumerate() {
+                let c = src.mono(at + j as f64 * stride);
+                num += t * c;
+                energy += c * c;
+            }
+            num / (energy + 1e-9).sqrt()
+        };
+
+        // 그대로 이어지는 자리가 범위 안이면 그게 후보 1순위 (템포 ≈ 1이면 거의 항상 여기)
+        let seek = STRETCH_SEEK as f64;
+        let mut best = nominal;
+        let mut best_score = f32::MIN;
+        if (target - nominal).abs() <= seek {
+            best = target;
+            best_score = score(target);
+        }
+        for d in -(STRETCH_SEEK as i64)..=STRETCH_SEEK as i64 {
+            let at = nominal + d as f64;
+            let s = score(at);
+            if s > best_score {
+                best_score = s;
+                best = at;
+            }
+        }
+        best
+    }
+}
+
+impl Default for Stretcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fade;
+
+    // 주기가 없는 신호 (처프) — 사인처럼 여러 자리가 똑같이 닮아 보이지 않게
+    fn chirp(frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let t = i as f64;
+                let l = (0.5 * (1e-5 * t * t + 0.01 * t).sin()) as f32;
+                let r = (0.4 * (7e-6 * t * t + 0.02 * t).cos()) as f32;
+                [l, r]
+            })
+            .collect()
+    }
+
+    fn params(read: Rate, speed: Rate) -> StretchParams {
+        StretchParams {
+            clip_id: 1,
+            base: 0,
+            rel0: 0,
+            src0: 0.0,
+            read,
+            speed,
+            reversed: false,
+        }
+    }
+
+    fn render(
+        st: &mut Stretcher,
+        src: &StretchSource,
+        p: &StretchParams,
+        rel: u64,
+        frames: usize,
+    ) -> Vec<f32> {
+        let mut out = vec![0.0; slots(frames)];
+        let mut env = ClipEnvelope::new(Fade::default(), Fade::default(), u64::MAX, rel);
+        st.render(src, p, rel, &mut out, &mut env);
+        out
+    }
+
+    fn max_diff(a: &[f32], b: &[f32]) -> f32 {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn read_equal_to_speed_matches_resample() {
+        let buf = chirp(40_000);
+        let src = StretchSource::new(&buf, 0);
+        let step = Rate::constant(1.25);
+        let p = params(step, step);
+        let frames = 20_000;
+        let out = render(&mut Stretcher::new(), &src, &p, 0, frames);
+
+        // 리샘플 경로: 출력 k에서 소스 k * step을 선형보간
+        let resampled: Vec<f32> = (0..frames)
+            .flat_map(|k| {
+                let (l, r) = src.at(step.advance(k as f64));
+                [l, r]
+            })
+            .collect();
+        assert!(max_diff(&out, &resampled) < 1e-4);
+    }
+
+    #[test]
+    fn reversed_source_reads_backwards() {
+        let buf = chirp(40_000);
+        let top = 39_999u64;
+        let fwd = StretchSource::new(&buf, 0);
+        let rev = StretchSource::reversed(&buf, 0, top);
+        let step = Rate::constant(0.9);
+        let p = StretchParams {
+            reversed: true,
+            ..params(step, step)
+        };
+        let frames = 20_000;
+        let out = render(&mut Stretcher::new(), &rev, &p, 0, frames);
+
+        // 역재생 리샘플 경로: 출력 k에서 소스 top - k * step
+        let resampled: Vec<f32> = (0..frames)
+            .flat_map(|k| {
+                let (l, r) = fwd.at(top as f64 - step.advance(k as f64));
+                [l, r]
+            })
+            .collect();
+        assert!(max_diff(&out, &resampled) < 1e-4);
+    }
+
+    #[test]
+    fn source_advances_by_speed() {
+        let buf = chirp(80_000);
+        let src = StretchSource::new(&buf, 0);
+        let frames = 16_384;
+        for speed in [0.5, 1.0, 2.0] {
+            let p = params(Rate::constant(1.0), Rate::constant(speed));
+            let mut st = Stretcher::new();
+            let out = render(&mut st, &src, &p, 0, frames);
+
+            // 마지막 grain은 speed * (출력 위치) 근처 소스에 놓임
+            let (at, _) = st.prev.unwrap();
+            let last = ((frames - 1) / HOP) as f64 * HOP as f64;
+            assert!(
+                (at - speed * last).abs() <= STRETCH_SEEK as f64,
+                "speed {speed}: {at}"
+            );
+
+            // 출력 끝까지 소리가 이어짐 (길이는 출력 프레임대로)
+            let tail = &out[slots(frames - HOP)..];
+            assert!(
+                tail.iter().map(|x| x * x).sum::<f32>() > 1.0,
+                "speed {speed}"
+            );
+        }
+    }
+
+    #[test]
+    fn source_range_covers_render() {
+        let buf = chirp(120_000);
+        let full = StretchSource::new(&buf, 0);
+        let ramp = Rate {
+            at: 0.8,
+            slope: 2e-5,
+        };
+        let p = params(Rate::constant(1.5), ramp);
+        let (rel, frames) = (10_000u64, CHUNK_STRETCH);
+
+        let (lo, hi) = Stretcher::source_range(&p, rel, frames);
+        let (lo, hi) = (lo as usize, (hi as usize).min(buf.len() / CHANNELS));
+        let part = StretchSource::new(&buf[slots(lo)..slots(hi)], lo as u64);
+
+        // 구간 밖을 무음으로 바꿔도 결과가 같아야 함
+        let a = render(&mut Stretcher::new(), &full, &p, rel, frames);
+        let b = render(&mut Stretcher::new(), &part, &p, rel, frames);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn split_render_is_continuous() {
+        let buf = chirp(80_000);
+        let src = StretchSource::new(&buf, 0);
+        let p = params(Rate::constant(1.2), Rate::constant(0.7));
+        let frames = 3 * CHUNK_STRETCH + 123;
+
+        let whole = render(&mut Stretcher::new(), &src, &p, 0, frames);
+        let mut st = Stretcher::new();
+        let mut pieces = Vec::new();
+        let mut rel = 0;
+        for n in [1_000, CHUNK_STRETCH, 77, frames - 1_077 - CHUNK_STRETCH] {
+            pieces.extend(render(&mut st, &src, &p, rel as u64, n));
+            rel += n;
+        }
+        assert_eq!(whole, pieces);
+    }
+
+    #[test]
+    fn restarts_on_jump_or_other_clip() {
+        let buf = chirp(80_000);
+        let src = StretchSource::new(&buf, 0);
+        let p = params(Rate::constant(1.0), Rate::constant(0.7));
+        let fresh = render(&mut Stretcher::new(), &src, &p, 20_000, 2_048);
+
+        // 이어지지 않는 위치면 그 자리에서 새로 시작한 것과 같음
+        let mut st = Stretcher::new();
+        render(&mut st, &src, &p, 0, 5_000);
+        assert_eq!(render(&mut st, &src, &p, 20_000, 2_048), fresh);
+
+        // 위치가 이어져도 다른 클립이면 새로 시작
+        let mut st = Stretcher::new();
+        let other = StretchParams { clip_id: 2, ..p };
+        render(&mut st, &src, &other, 0, 20_000);
+        assert_eq!(render(&mut st, &src, &p, 20_000, 2_048), fresh);
+    }
+}
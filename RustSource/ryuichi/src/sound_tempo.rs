@@ -0,0 +1,445 @@
+use crate::unit::*;
+use crate::Engine;
+
+// -------------------------
+// 템포 맵: 기본 BPM + 시간에 따른 템포 이벤트 (계단 또는 직선 램프)
+// 타임라인 프레임 t에서의 템포 비율(bpm / BASE_BPM)을 적분하면 그동안 소스가 나아간 양
+// -------------------------
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TempoEvent {
+    pub frame: u64, //타임라인 프레임 (엔진 샘플레이트 기준)
+    pub bpm: f32,
+    pub ramp: bool, //true = 앞 이벤트에서 여기까지 직선으로 변함, false = 여기서 바로 바뀜
+}
+
+// 이벤트 사이 한 조각: start부터 ratio + slope * (t - start)
+#[derive(Clone, Copy)]
+struct TempoPiece {
+    start: u64,
+    ratio: f64,
+    slope: f64,
+    warped: f64, //0부터 start까지 템포 비율 적분
+}
+
+#[derive(Clone)]
+pub struct TempoMap {
+    base_bpm: f32,
+    events: Vec<TempoEvent>, //frame 순, 같은 frame은 하나만
+    pieces: Vec<TempoPiece>, //첫 조각은 항상 0에서 시작
+}
+
+#[inline]
+fn bpm_ratio(bpm: f32) -> f64 {
+    (bpm / BASE_BPM).clamp(MIN_TEMPO_RATIO, MAX_TEMPO_RATIO) as f64
+}
+
+impl TempoMap {
+    pub fn new(base_bpm: f32, mut events: Vec<TempoEvent>) -> Self {
+        // 같은 위치면 나중에 넣은 것만 남김
+        events.reverse();
+        events.sort_by_key(|e| e.frame);
+        events.dedup_by_key(|e| e.frame);
+
+        let mut pieces = vec![TempoPiece {
+            start: 0,
+            ratio: bpm_ratio(base_bpm),
+            slope: 0.0,
+            warped: 0.0,
+        }];
+        for e in events.iter() {
+            let ratio = bpm_ratio(e.bpm);
+            let last = pieces.len() - 1;
+            let prev = pieces[last];
+            if e.frame == prev.start {
+                pieces[last].ratio = ratio; // 0에 있는 이벤트는 기본 BPM 대신
+                continue;
+            }
+            let len = (e.frame - prev.start) as f64;
+            let slope = if e.ramp {
+                (ratio - prev.ratio) / len
+            } else {
+                0.0
+            };
+            pieces[last].slope = slope;
+            pieces.push(TempoPiece {
+                start: e.frame,
+                ratio,
+                slope: 0.0,
+                warped: prev.warped + prev.ratio * len + 0.5 * slope * len * len,
+            });
+        }
+        Self {
+            base_bpm,
+            events,
+            pieces,
+        }
+    }
+
+    pub fn base_bpm(&self) -> f32 {
+        self.base_bpm
+    }
+
+    pub fn events(&self) -> &[TempoEvent] {
+        &self.events
+    }
+
+    fn piece(&self, t: u64) -> &TempoPiece {
+        let i = self.pieces.partition_point(|p| p.start <= t);
+        &self.pieces[i.saturating_sub(1)]
+    }
+
+    pub fn bpm_at(&self, t: u64) -> f32 {
+        self.rate_at(t).at as f32 * BASE_BPM
+    }
+
+    // t에서의 템포 비율과 프레임당 변화량 (다음 이벤트 전까지 직선)
+    pub fn rate_at(&self, t: u64) -> Rate {
+        let p = self.piece(t);
+        Rate {
+            at: p.ratio + p.slope * (t - p.start) as f64,
+            slope: p.slope,
+        }
+    }
+
+    // t 다음 이벤트 위치 (구간을 여기서 끊어야 속도가 직선으로 유지됨)
+    pub fn next_change(&self, t: u64) -> u64 {
+        let i = self.pieces.partition_point(|p| p.start <= t);
+        self.pieces.get(i).map_or(u64::MAX, |p| p.start)
+    }
+
+    // 0부터 t까지 적분 (0 앞은 처음 템포 그대로 이어진다고 봄)
+    fn warped(&self, t: f64) -> f64 {
+        if t <= 0.0 {
+            return t * self.pieces[0].ratio;
+        }
+        let p = self.piece(t as u64);
+        let x = t - p.start as f64;
+        p.warped + p.ratio * x + 0.5 * p.slope * x * x
+    }
+
+    // pos 앞 frames 동안의 템포 비율 적분 (템포가 60이면 frames 그대로)
+    pub fn span(&self, pos: u64, frames: u64) -> f64 {
+        self.warped(pos as f64) - self.warped(pos as f64 - frames as f64)
+    }
+
+    // warped의 역: 적분이 w가 되는 프레임 (소수)
+    fn unwarped(&self, w: f64) -> f64 {
+        let first = &self.pieces[0];
+        if w <= 0.0 {
+            return w / first.ratio;
+        }
+        let i = self.pieces.partition_point(|p| p.warped <= w);
+        let p = &self.pieces[i.saturating_sub(1)];
+        let rate = Rate {
+            at: p.ratio,
+            slope: p.slope,
+        };
+        p.start as f64 + rate.frames_for(w - p.warped)
+    }
+
+    // 0부터 t까지 지난 박 수 (BASE_BPM에서는 1초 = BASE_BPM / 60 박)
+    pub fn beats_at(&self, t: f64, sr: u32) -> f64 {
+        self.warped(t) * BASE_BPM as f64 / (60.0 * sr.max(1) as f64)
+    }
+
+    // beats_at의 역 (소수 프레임)
+    pub fn frame_at_beats(&self, beats: f64, sr: u32) -> f64 {
+        self.unwarped(beats * 60.0 * sr.max(1) as f64 / BASE_BPM as f64)
+    }
+
+    // from부터 적분이 span이 될 때까지의 프레임 수 (span의 역)
+    pub fn frames_for_span(&self, from: f64, span: f64) -> f64 {
+        self.unwarped(self.warped(from) + span) - from
+    }
+
+    // 샘플레이트 변경: 이벤트 위치만 새 레이트로 환산
+    pub fn rescaled(&self, old_sr: u32, new_sr: u32) -> Self {
+        let events = self
+            .events
+            .iter()
+            .map(|e| TempoEvent {
+                frame: rescale_frames(e.frame, old_sr, new_sr),
+                ..*e
+            })
+            .collect();
+        Self::new(self.base_bpm, events)
+    }
+}
+
+// -------------------------
+// 출력 프레임 k마다 변하는 소스 진행 속도 (템포 램프 구간에서는 직선, 아니면 slope = 0)
+// -------------------------
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rate {
+    pub at: f64,
+    pub slope: f64,
+}
+
+impl Rate {
+    pub fn constant(at: f64) -> Self {
+        Self { at, slope: 0.0 }
+    }
+
+    pub fn scale(self, s: f64) -> Self {
+        Self {
+            at: self.at * s,
+            slope: self.slope * s,
+        }
+    }
+
+    #[inline]
+    pub fn step(&self, k: f64) -> f64 {
+        self.at + self.slope * k
+    }
+
+    // 0..k 동안 나아간 양
+    #[inline]
+    pub fn advance(&self, k: f64) -> f64 {
+        self.at * k + 0.5 * self.slope * k * k
+    }
+
+    // amount만큼 나아가는 데 걸리는 출력 프레임 (못 닿으면 무한대)
+    pub fn frames_for(&self, amount: f64) -> f64 {
+        let disc = self.at * self.at + 2.0 * self.slope * amount;
+        if disc < 0.0 {
+            return f64::INFINITY;
+        }
+        let den = self.at + disc.sqrt();
+        if den <= 0.0 {
+            return f64::INFINITY;
+        }
+        2.0 * amount / den
+    }
+}
+
+impl Engine {
+    // 템포 이벤트 편집: f가 true를 돌려주면 기록 남기고 새 맵으로 리버퍼 (BPM과 같은 경로)
+    pub fn edit_tempo(&mut self, f: impl FnOnce(&mut Vec<TempoEvent>) -> bool) -> bool {
+        let cur = self.tempo();
+        let mut events = cur.events().to_vec();
+        if !f(&mut events) {
+            return false;
+        }
+        self.record_tempo_change(cur.events().to_vec());
+        self.set_tempo_map(TempoMap::new(cur.base_bpm(), events));
+        self.rebuffer_current();
+        true
+    }
+}
+
+// frame에 템포 이벤트 넣기 (같은 위치면 바꿈), ramp = 앞 이벤트에서 여기까지 직선으로
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_tempo_set_event(
+    engine: *mut Engine,
+    frame: u64,
+    bpm: f32,
+    ramp: bool,
+) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &mut *engine };
+    if !bpm.is_finite() {
+        return false;
+    }
+    let e = TempoEvent {
+        frame,
+        bpm: bpm.clamp(MIN_BPM, MAX_BPM),
+        ramp,
+    };
+    eng.edit_tempo(|events| {
+        events.retain(|x| x.frame != frame);
+        events.push(e);
+        true
+    })
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_tempo_remove_event(engine: *mut Engine, frame: u64) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &mut *engine };
+    eng.edit_tempo(|events| {
+        let n = events.len();
+        events.retain(|x| x.frame != frame);
+        events.len() != n
+    })
+}
+
+// 이벤트를 다 지우고 기본 BPM만 남김
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_tempo_clear(engine: *mut Engine) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &mut *engine };
+    eng.edit_tempo(|events| {
+        let had = !events.is_empty();
+        events.clear();
+        had
+    })
+}
+
+// UI 그리기용: 이벤트는 frame 순, index가 범위 밖이면 0 / false
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_tempo_event_count(engine: *const Engine) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.tempo().events().len() as u32
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_tempo_event_frame(engine: *const Engine, index: u32) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.tempo()
+        .events()
+        .get(index as usize)
+        .map_or(0, |e| e.frame)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_tempo_event_bpm(engine: *const Engine, index: u32) -> f32 {
+    if engine.is_null() {
+        return 0.0;
+    }
+    let eng = unsafe { &*engine };
+    eng.tempo()
+        .events()
+        .get(index as usize)
+        .map_or(0.0, |e| e.bpm)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_tempo_event_ramp(engine: *const Engine, index: u32) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.tempo()
+        .events()
+        .get(index as usize)
+        .is_some_and(|e| e.ramp)
+}
+
+// frame에서의 실제 템포 (램프 중간값 포함)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_tempo_bpm_at(engine: *const Engine, frame: u64) -> f32 {
+    if engine.is_null() {
+        return 0.0;
+    }
+    let eng = unsafe { &*engine };
+    eng.tempo().bpm_at(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ev(frame: u64, bpm: f32, ramp: bool) -> TempoEvent {
+        TempoEvent { frame, bpm, ramp }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6 * b.abs().max(1.0)
+    }
+
+    // 60 → 1000에서 120으로 바로 → 3000까지 60으로 램프
+    fn map() -> TempoMap {
+        TempoMap::new(60.0, vec![ev(1_000, 120.0, false), ev(3_000, 60.0, true)])
+    }
+
+    #[test]
+    fn step_integral() {
+        let m = TempoMap::new(60.0, vec![ev(1_000, 120.0, false), ev(2_000, 30.0, false)]);
+        assert!(close(m.span(1_000, 1_000), 1_000.0));
+        assert!(close(m.span(1_500, 1_000), 500.0 + 1_000.0));
+        assert!(close(m.span(2_000, 1_000), 2_000.0));
+        assert!(close(m.span(2_500, 1_000), 1_000.0 + 250.0));
+        // 0 앞은 처음 템포 그대로
+        assert!(close(m.span(0, 500), 500.0));
+    }
+
+    #[test]
+    fn ramp_integral() {
+        let m = map();
+        // 1000..3000: 2 → 1 직선, 평균 1.5
+        assert!(close(m.span(3_000, 2_000), 3_000.0));
+        assert!(close(m.span(2_000, 1_000), 1_750.0));
+        // 램프가 끝나면 마지막 이벤트 템포로 유지
+        assert!(close(m.span(5_000, 1_000), 1_000.0));
+    }
+
+    #[test]
+    fn ramp_pieces() {
+        let m = map();
+        let r = m.rate_at(2_000);
+        assert!(close(r.at, 1.5));
+        assert!(close(r.slope, -1.0 / 2_000.0));
+        assert_eq!(m.bpm_at(2_000), 90.0);
+        // 조각 안에서는 rate의 적분이 span과 같음
+        assert!(close(r.advance(500.0), m.span(2_500, 500)));
+        assert_eq!(m.rate_at(500), Rate::constant(1.0));
+        assert_eq!(m.rate_at(4_000), Rate::constant(1.0));
+    }
+
+    #[test]
+    fn boundary_frames() {
+        let m = map();
+        // 계단은 이벤트 프레임부터 새 템포
+        assert_eq!(m.bpm_at(999), 60.0);
+        assert_eq!(m.bpm_at(1_000), 120.0);
+        // 램프 끝 프레임에서 정확히 목표 템포
+        assert!(close(m.rate_at(3_000).at, 1.0));
+        assert!(close(m.rate_at(2_999).at, 1.0 + 1.0 / 2_000.0));
+
+        // 0에 있는 이벤트는 기본 BPM 대신, 같은 위치는 나중 것만
+        let m = TempoMap::new(
+            60.0,
+            vec![ev(0, 90.0, false), ev(10, 30.0, false), ev(10, 45.0, true)],
+        );
+        assert_eq!(m.bpm_at(0), 90.0);
+        assert_eq!(m.events().len(), 2);
+        assert_eq!(m.bpm_at(10), 45.0);
+        assert!(m.events()[1].ramp);
+    }
+
+    #[test]
+    fn next_change() {
+        let m = map();
+        assert_eq!(m.next_change(0), 1_000);
+        assert_eq!(m.next_change(999), 1_000);
+        assert_eq!(m.next_change(1_000), 3_000);
+        assert_eq!(m.next_change(3_000), u64::MAX);
+        assert_eq!(TempoMap::new(60.0, Vec::new()).next_change(0), u64::MAX);
+    }
+
+    #[test]
+    fn frames_beats_round_trip() {
+        let m = map();
+        let sr = 48_000;
+        for t in [
+            -250.0, 0.0, 1.0, 999.5, 1_000.0, 1_234.5, 2_000.0, 2_999.0, 3_000.0, 7_777.25,
+        ] {
+            let b = m.beats_at(t, sr);
+            assert!(close(m.frame_at_beats(b, sr), t), "{t}");
+        }
+        // BASE_BPM 60에서 sr 프레임 = 1박
+        let flat = TempoMap::new(60.0, Vec::new());
+        assert!(close(flat.beats_at(sr as f64, sr), 1.0));
+        assert!(close(flat.frame_at_beats(2.0, sr), 2.0 * sr as f64));
+    }
+}
@@ -0,0 +1,517 @@
+use crate::unit::*;
+use crate::HistoryOp;
+use crate::TempoMap;
+use crate::TrackList;
+use crate::{Clip, Engine};
+use std::sync::Arc;
+
+// -------------------------
+// 박자표: 한 마디 = num 박, 한 박 = den분음표
+// BPM은 4분음표 기준이라 박자표 한 박 = 4/den BPM 박 (6/8 한 마디 = BPM 3박)
+// -------------------------
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimeSignature {
+    pub num: u32,
+    pub den: u32,
+}
+
+impl TimeSignature {
+    pub fn new(num: u32, den: u32) -> Option<Self> {
+        if !(1..=MAX_METER_NUM).contains(&num)
+            || !(1..=MAX_METER_DEN).contains(&den)
+            || !den.is_power_of_two()
+        {
+            return None;
+        }
+        Some(Self { num, den })
+    }
+
+    // 박자표 한 박의 길이 (BPM 박 단위)
+    pub fn beat_len(&self) -> f64 {
+        4.0 / self.den as f64
+    }
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        Self { num: 4, den: 4 }
+    }
+}
+
+// 음악 시간 (모두 0부터: 첫 마디 첫 박 = 0:0:0), 박/틱은 박자표 박 기준
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MusicalTime {
+    pub bar: u64,
+    pub beat: u32,
+    pub tick: u32,
+}
+
+// 프레임 ↔ 틱/마디:박 변환 (편집을 적용하는 시점의 템포 맵 + 샘플레이트 + 박자표)
+// UI 격자/스냅/박 고정이 모두 여기서 계산함
+pub struct TimeGrid {
+    tempo: Arc<TempoMap>,
+    sr: u32,
+    meter: TimeSignature,
+}
+impl TimeGrid {
+    pub fn new(tempo: Arc<TempoMap>, sr: u32) -> Self {
+        Self {
+            tempo,
+            sr,
+            meter: TimeSignature::default(),
+        }
+    }
+
+    pub fn with_meter(mut self, meter: TimeSignature) -> Self {
+        self.meter = meter;
+        self
+    }
+
+    pub fn tempo(&self) -> &TempoMap {
+        &self.tempo
+    }
+
+    pub fn sr(&self) -> u32 {
+        self.sr
+    }
+
+    // 박 고정용 틱은 BPM 박 기준 (박자표를 바꿔도 고정된 클립은 제자리)
+    pub fn ticks_at(&self, frame: f64) -> f64 {
+        self.tempo.beats_at(frame, self.sr) * TICKS_PER_BEAT as f64
+    }
+
+    pub fn frame_at_ticks(&self, ticks: f64) -> f64 {
+        self.tempo
+            .frame_at_beats(ticks / TICKS_PER_BEAT as f64, self.sr)
+    }
+
+    // 프레임 → 박자표 박 (4/4면 BPM 박과 같음)
+    pub fn meter_beats_at(&self, frame: u64) -> f64 {
+        self.tempo.beats_at(frame as f64, self.sr) / self.meter.beat_len()
+    }
+
+    pub fn frame_at_meter_beats(&self, beats: f64) -> u64 {
+        let f = self
+            .tempo
+            .frame_at_beats(beats * self.meter.beat_len(), self.sr);
+        f.round().max(0.0) as u64
+    }
+
+    pub fn musical_at(&self, frame: u64) -> MusicalTime {
+        let num = self.meter.num as u64;
+        // 박 경계에 딱 맞는 프레임이 부동소수 오차로 앞 틱에 걸리지 않게 살짝 올림
+        let ticks = (self.meter_beats_at(frame) * TICKS_PER_BEAT as f64 + 1e-6).floor() as u64;
+        let beats = ticks / TICKS_PER_BEAT as u64;
+        MusicalTime {
+            bar: beats / num,
+            beat: (beats % num) as u32,
+            tick: (ticks % TICKS_PER_BEAT as u64) as u32,
+        }
+    }
+
+    // 박/틱이 범위를 넘으면 그만큼 뒤로 (4/4에서 0:5:0 = 1:1:0)
+    pub fn frame_at_musical(&self, t: MusicalTime) -> u64 {
+        let num = self.meter.num as f64;
+        let beats = t.bar as f64 * num + t.beat as f64 + t.tick as f64 / TICKS_PER_BEAT as f64;
+        self.frame_at_meter_beats(beats)
+    }
+
+    // 가장 가까운 격자로: division = 박자표 한 박을 나누는 수, 0이면 마디 단위
+    pub fn snap(&self, frame: u64, division: u32) -> u64 {
+        let grid = if division == 0 {
+            self.meter.num as f64
+        } else {
+            1.0 / division as f64
+        };
+        let beats = (self.meter_beats_at(frame) / grid).round() * grid;
+        self.frame_at_meter_beats(beats)
+    }
+}
+
+// -------------------------
+// 박에 고정된 클립의 위치 (틱) — 템포가 바뀌면 프레임 값을 여기서 다시 계산
+// offset/loop_len도 틱으로 들고 있어야 분할한 뒷조각/반복 길이가 박에 맞게 따라감
+// -------------------------
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct BeatAnchor {
+    pub start: u64,
+    pub len: u64,
+    pub offset: u64,   //tl_offset에 해당 (앞에서 잘려 나간 길이)
+    pub loop_len: u64, //0 = 반복 안 함
+}
+
+impl Clip {
+    // 프레임 단위 편집 뒤: 지금 프레임 위치를 가장 가까운 틱으로 옮겨 기록 (고정 안 된 클립은 그대로)
+    pub(crate) fn sync_anchor(&mut self, grid: &TimeGrid) {
+        if self.anchor.is_none() {
+            return;
+        }
+        let start = grid.ticks_at(self.tl_start as f64);
+        let end = grid.ticks_at(self.tl_start.saturating_add(self.tl_len) as f64);
+        let front = grid.ticks_at(self.tl_start as f64 - self.tl_offset as f64);
+        let looped = grid.ticks_at(self.tl_start.saturating_add(self.loop_len) as f64);
+        let start_t = start.round().max(0.0) as u64;
+        self.anchor = Some(BeatAnchor {
+            start: start_t,
+            len: (end.round().max(0.0) as u64).saturating_sub(start_t).max(1),
+            offset: (start - front).round().max(0.0) as u64,
+            loop_len: if self.loop_len > 0 {
+                ((looped - start).round() as u64).max(1)
+            } else {
+                0
+            },
+        });
+        self.retime(grid);
+    }
+
+    // 틱 → 프레임 (템포 변경 뒤, 되돌리기로 되살릴 때), 바뀌었으면 true
+    pub(crate) fn retime(&mut self, grid: &TimeGrid) -> bool {
+        let Some(a) = self.anchor else {
+            return false;
+        };
+        let start = grid.frame_at_ticks(a.start as f64);
+        let tl_start = start.round().max(0.0) as u64;
+        let end = grid.frame_at_ticks((a.start + a.len) as f64).round() as u64;
+        let tl_len = end.saturating_sub(tl_start).max(1);
+        let tl_offset = if a.offset > 0 {
+            (start - grid.frame_at_ticks(a.start as f64 - a.offset as f64)).round() as u64
+        } else {
+            0
+        };
+        let loop_len = if a.loop_len > 0 {
+            ((grid.frame_at_ticks((a.start + a.loop_len) as f64) - start).round() as u64).max(1)
+        } else {
+            0
+        };
+        let changed = (tl_start, tl_len, tl_offset, loop_len)
+            != (self.tl_start, self.tl_len, self.tl_offset, self.loop_len);
+        self.tl_start = tl_start;
+        self.tl_len = tl_len;
+        self.tl_offset = tl_offset;
+        self.loop_len = loop_len;
+        changed
+    }
+}
+
+// 템포가 바뀌면 박에 고정된 클립을 전부 새 프레임 위치로 (키도 같이 바뀜)
+pub(crate) fn retime_clips(tracks: &TrackList, grid: &TimeGrid) {
+    for slot in tracks.snapshot().iter() {
+        let Ok(mut tr) = slot.timeline.lock() else {
+            continue;
+        };
+        let moved: Vec<_> = tr
+            .clips
+            .iter()
+            .filter(|(_, c)| c.anchor.is_some())
+            .map(|(k, _)| *k)
+            .collect();
+        for key in moved {
+            if let Some(mut clip) = tr.clips.remove(&key) {
+                clip.retime(grid);
+                tr.clips.insert((clip.tl_start, clip.id), clip);
+            }
+        }
+    }
+}
+
+impl Engine {
+    pub fn time_grid(&self) -> TimeGrid {
+        TimeGrid::new(self.tempo(), self.play_time_manager.sr()).with_meter(self.time_signature())
+    }
+
+    // 템포 맵을 바꾸는 유일한 경로: 박에 고정된 클립도 같이 옮김 (재생은 호출한 쪽이 리버퍼)
+    pub(crate) fn set_tempo_map(&self, map: TempoMap) {
+        self.real_time_params.set_tempo(map);
+        let grid = self.time_grid();
+        self.with_history(|_, tracks| retime_clips(tracks, &grid));
+    }
+
+    pub fn time_signature(&self) -> TimeSignature {
+        self.meter.load()
+    }
+
+    // 박자표는 재생에 영향이 없으므로 리버퍼 없이 바로 바꾸고 기록만 남김
+    pub fn set_time_signature(&self, ts: TimeSignature) -> bool {
+        let prev = self.meter.swap(ts);
+        if prev != ts {
+            self.with_history(|h, _| h.record(vec![HistoryOp::Meter(prev)]));
+        }
+        true
+    }
+
+    // 프레임 → BPM 박 (템포 맵 반영, 박자표와 무관)
+    pub fn beats_at(&self, frame: u64) -> f64 {
+        self.tempo()
+            .beats_at(frame as f64, self.play_time_manager.sr())
+    }
+
+    pub fn frame_at_beats(&self, beats: f64) -> u64 {
+        let f = self
+            .tempo()
+            .frame_at_beats(beats, self.play_time_manager.sr());
+        f.round().max(0.0) as u64
+    }
+
+    pub fn musical_at(&self, frame: u64) -> MusicalTime {
+        self.time_grid().musical_at(frame)
+    }
+
+    pub fn frame_at_musical(&self, t: MusicalTime) -> u64 {
+        self.time_grid().frame_at_musical(t)
+    }
+
+    pub fn frames_to_seconds(&self, frame: u64) -> f64 {
+        frame as f64 / self.play_time_manager.sr().max(1) as f64
+    }
+
+    pub fn seconds_to_frames(&self, secs: f64) -> u64 {
+        (secs * self.play_time_manager.sr() as f64).round().max(0.0) as u64
+    }
+
+    pub fn snap_to_grid(&self, frame: u64, division: u32) -> u64 {
+        self.time_grid().snap(frame, division)
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_set_signature(engine: *mut Engine, num: u32, den: u32) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    match TimeSignature::new(num, den) {
+        Some(ts) => eng.set_time_signature(ts),
+        None => false,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_signature_num(engine: *const Engine) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.time_signature().num
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_signature_den(engine: *const Engine) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.time_signature().den
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_frames_to_beats(engine: *const Engine, frame: u64) -> f64 {
+    if engine.is_null() {
+        return 0.0;
+    }
+    let eng = unsafe { &*engine };
+    eng.beats_at(frame)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_beats_to_frames(engine: *const Engine, beats: f64) -> u64 {
+    if engine.is_null() || !beats.is_finite() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.frame_at_beats(beats)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_frames_to_seconds(engine: *const Engine, frame: u64) -> f64 {
+    if engine.is_null() {
+        return 0.0;
+    }
+    let eng = unsafe { &*engine };
+    eng.frames_to_seconds(frame)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_seconds_to_frames(engine: *const Engine, secs: f64) -> u64 {
+    if engine.is_null() || !secs.is_finite() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.seconds_to_frames(secs)
+}
+
+// UI 표시용 마디:박:틱 — 마디와 박은 1부터 (1:1:0 = 맨 앞), 틱은 0부터
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_bar_at(engine: *const Engine, frame: u64) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    (eng.musical_at(frame).bar + 1) as u32
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_beat_at(engine: *const Engine, frame: u64) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.musical_at(frame).beat + 1
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_tick_at(engine: *const Engine, frame: u64) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.musical_at(frame).tick
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_bbt_to_frames(
+    engine: *const Engine,
+    bar: u32,
+    beat: u32,
+    tick: u32,
+) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.frame_at_musical(MusicalTime {
+        bar: bar.saturating_sub(1) as u64,
+        beat: beat.saturating_sub(1),
+        tick,
+    })
+}
+
+// division = 박자표 한 박을 나누는 격자 수 (4 = 16분음표 @ 4/4, 32분음표 @ 6/8), 0 = 마디
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_time_snap(engine: *const Engine, frame: u64, division: u32) -> u64 {
+    if engine.is_null() {
+        return frame;
+    }
+    let eng = unsafe { &*engine };
+    eng.snap_to_grid(frame, division)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TempoEvent;
+
+    fn bbt(bar: u64, beat: u32, tick: u32) -> MusicalTime {
+        MusicalTime { bar, beat, tick }
+    }
+
+    #[test]
+    fn grid_ticks_frames() {
+        let sr = 48_000;
+        let flat = TimeGrid::new(Arc::new(TempoMap::new(BASE_BPM, Vec::new())), sr);
+        // BASE_BPM 60: 1초 = 1박
+        assert!((flat.ticks_at(sr as f64) - TICKS_PER_BEAT as f64).abs() < 1e-9);
+        assert!((flat.frame_at_ticks(TICKS_PER_BEAT as f64 / 2.0) - 24_000.0).abs() < 1e-9);
+
+        let events = vec![
+            TempoEvent {
+                frame: 48_000,
+                bpm: 120.0,
+                ramp: false,
+            },
+            TempoEvent {
+                frame: 96_000,
+                bpm: 120.0,
+                ramp: false,
+            },
+            TempoEvent {
+                frame: 144_000,
+                bpm: 90.0,
+                ramp: true,
+            },
+        ];
+        let grid = TimeGrid::new(Arc::new(TempoMap::new(BASE_BPM, events)), sr);
+        // 계단 뒤 1초 = 2박
+        let t = grid.ticks_at(96_000.0);
+        assert!((t - 3.0 * TICKS_PER_BEAT as f64).abs() < 1e-6);
+        for ticks in [0.0, 1.0, 959.5, 960.0, 2_000.0, 5_432.1, 20_000.0] {
+            let f = grid.frame_at_ticks(ticks);
+            assert!((grid.ticks_at(f) - ticks).abs() < 1e-6, "{ticks}");
+        }
+    }
+
+    #[test]
+    fn bbt_follows_meter() {
+        let eng = Engine::with_track_count(0).unwrap();
+        let beat = |b: f64| eng.frame_at_beats(b);
+
+        assert_eq!(eng.musical_at(0), bbt(0, 0, 0));
+        assert_eq!(eng.musical_at(beat(5.0)), bbt(1, 1, 0));
+        assert_eq!(eng.musical_at(beat(5.5)), bbt(1, 1, TICKS_PER_BEAT / 2));
+        assert_eq!(eng.frame_at_musical(bbt(1, 1, 0)), beat(5.0));
+        // 범위를 넘는 박은 다음 마디로 넘어감
+        assert_eq!(eng.frame_at_musical(bbt(0, 5, 0)), beat(5.0));
+
+        // 박자표만 바꾸면 같은 프레임이 다른 마디:박으로
+        assert!(eng.set_time_signature(TimeSignature::new(3, 4).unwrap()));
+        assert_eq!(eng.musical_at(beat(5.0)), bbt(1, 2, 0));
+        assert_eq!(eng.frame_at_musical(bbt(1, 2, 0)), beat(5.0));
+        // 분모가 박 길이: 7/8 한 박 = BPM 반 박, 한 마디 = BPM 3.5박
+        assert!(eng.set_time_signature(TimeSignature::new(7, 8).unwrap()));
+        assert_eq!(eng.musical_at(beat(2.5)), bbt(0, 5, 0));
+        assert_eq!(eng.musical_at(beat(3.5)), bbt(1, 0, 0));
+        assert_eq!(eng.musical_at(beat(5.0)), bbt(1, 3, 0));
+        assert_eq!(eng.musical_at(beat(5.25)), bbt(1, 3, TICKS_PER_BEAT / 2));
+        assert_eq!(eng.frame_at_musical(bbt(1, 3, 0)), beat(5.0));
+        // 2/2 한 박 = BPM 2박
+        assert!(eng.set_time_signature(TimeSignature::new(2, 2).unwrap()));
+        assert_eq!(eng.musical_at(beat(5.0)), bbt(1, 0, TICKS_PER_BEAT / 2));
+        assert_eq!(eng.frame_at_musical(bbt(2, 0, 0)), beat(8.0));
+
+        assert!(TimeSignature::new(0, 4).is_none());
+        assert!(TimeSignature::new(4, 6).is_none());
+        assert!(TimeSignature::new(MAX_METER_NUM + 1, 4).is_none());
+    }
+
+    #[test]
+    fn meter_does_not_move_anchors() {
+        // 박 고정 틱은 BPM 박 기준이라 박자표와 상관없이 같은 프레임
+        let tempo = Arc::new(TempoMap::new(BASE_BPM, Vec::new()));
+        let quarter = TimeGrid::new(tempo.clone(), 48_000);
+        let eighth = TimeGrid::new(tempo, 48_000).with_meter(TimeSignature::new(6, 8).unwrap());
+        assert_eq!(quarter.ticks_at(72_000.0), eighth.ticks_at(72_000.0));
+        assert_eq!(eighth.meter_beats_at(72_000), 3.0);
+        assert_eq!(quarter.meter_beats_at(72_000), 1.5);
+    }
+
+    #[test]
+    fn snap_to_grid() {
+        let eng = Engine::with_track_count(0).unwrap();
+        let beat = |b: f64| eng.frame_at_beats(b);
+
+        assert_eq!(eng.snap_to_grid(beat(2.4), 1), beat(2.0));
+        assert_eq!(eng.snap_to_grid(beat(2.6), 1), beat(3.0));
+        assert_eq!(eng.snap_to_grid(beat(2.4), 4), beat(2.5));
+        // 0 = 마디 (4/4)
+        assert_eq!(eng.snap_to_grid(beat(1.9), 0), 0);
+        assert_eq!(eng.snap_to_grid(beat(2.1), 0), beat(4.0));
+        assert!(eng.set_time_signature(TimeSignature::new(3, 4).unwrap()));
+        assert_eq!(eng.snap_to_grid(beat(4.6), 0), beat(6.0));
+        // 6/8: 마디 = BPM 3박, division은 8분음표를 나눔
+        assert!(eng.set_time_signature(TimeSignature::new(6, 8).unwrap()));
+        assert_eq!(eng.snap_to_grid(beat(4.4), 0), beat(3.0));
+        assert_eq!(eng.snap_to_grid(beat(2.3), 1), beat(2.5));
+        assert_eq!(eng.snap_to_grid(beat(2.3), 2), beat(2.25));
+    }
+}
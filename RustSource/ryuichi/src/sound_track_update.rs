@@ -1,10 +1,117 @@
 use crate::Clip;
+use crate::ClipKey;
+use crate::ClipRef;
+use crate::EditCommand;
 use crate::Engine;
+use crate::MixerParam;
+use crate::SpeakerLayout;
+use crate::TimeGrid;
+use crate::TrackConfig;
+use crate::TrackTimeline;
+use crate::{Fade, FadeCurve};
+use crate::{PitchMode, TempoMode};
+use crate::{MAX_BPM, MAX_CLIP_PITCH_CENTS, MAX_OUT_CHANNELS, MIN_BPM};
 use std::ffi::CStr;
 use std::os::raw::c_char;
 use std::sync::atomic::Ordering;
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_track_count(engine: *const Engine) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.tracks.len() as u32
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_track_add(engine: *mut Engine) -> i32 {
+    if engine.is_null() {
+        return -1;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let tk = match TrackConfig::with_ring_frames(eng.ring_frames()) {
+        Ok(tk) => tk,
+        Err(_) => return -1,
+    };
+    match eng.insert_track(usize::MAX, tk) {
+        Some(idx) => idx as i32, //새 트랙 번호
+        None => -1,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_track_insert(engine: *mut Engine, number: i32) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let idx = match usize::try_from(number) {
+        Ok(number) => number,
+        Err(_) => return false,
+    };
+    if idx > eng.tracks.len() {
+        return false;
+    }
+    let tk = match TrackConfig::with_ring_frames(eng.ring_frames()) {
+        Ok(tk) => tk,
+        Err(_) => return false,
+    };
+    eng.insert_track(idx, tk).is_some()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_track_remove(engine: *mut Engine, number: i32) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let idx = match usize::try_from(number) {
+        Ok(number) => number,
+        Err(_) => return false,
+    };
+    eng.remove_track(idx)
+}
+
+// 트랙 번호/믹서 값은 두고 클립만 비움 (되돌리기 한 번)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_track_clear_clips(engine: *mut Engine, number: i32) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let Some(track) = eng.track_id_at(number) else {
+        return false;
+    };
+    eng.clear_track_clips(track)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_track_move(engine: *mut Engine, from: i32, to: i32) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let (from, to) = match (usize::try_from(from), usize::try_from(to)) {
+        (Ok(f), Ok(t)) => (f, t),
+        _ => return false,
+    };
+    if from == to {
+        return from < eng.tracks.len();
+    }
+    eng.move_track(from, to)
+}
+
+// src_start/src_end: 소스 파일에서 쓸 구간 (소스 샘플레이트 기준 프레임, src_end == 0 이면 파일 끝까지)
+// 반환: 새 클립 id (0 = 실패), 이후 편집은 이 id로
+#[no_mangle]
+#[allow(clippy::too_many_arguments, clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_sound_add_clip(
     engine: *mut Engine,
     number: i32,
@@ -12,138 +119,542 @@ pub extern "C" fn rust_sound_add_clip(
     tl_start: u64,
     tl_len: u64,
     src: u32,
+    src_start: u64,
+    src_end: u64,
+) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let Some(track) = eng.track_id_at(number) else {
+        return 0;
+    };
+    if tl_len == 0 || path.is_null() {
+        return 0;
+    }
+    if src_end != 0 && src_end <= src_start {
+        return 0;
+    }
+
+    let c_str = unsafe { CStr::from_ptr(path) };
+    let path_str = c_str.to_string_lossy().into_owned();
+
+    let id = eng.alloc_clip_id();
+    let clip = Clip::new(id, path_str, src, tl_start, tl_len, src_start, src_end);
+    if eng.submit_edit(EditCommand::AddClip { track, clip }) {
+        id
+    } else {
+        0
+    }
+}
+
+// 기존 클립의 소스 구간만 바꿈 (앞부분 트림/긴 녹음에서 일부만 쓰기)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_set_clip_source(
+    engine: *mut Engine,
+    clip: u64,
+    src_start: u64,
+    src_end: u64,
 ) -> bool {
     if engine.is_null() {
         return false;
     }
     let eng: &mut Engine = unsafe { &mut *engine };
-    let idx = match usize::try_from(number) {
-        Ok(number) => number,
-        Err(_) => return false,
+    if src_end != 0 && src_end <= src_start {
+        return false;
+    }
+    eng.submit_edit(EditCommand::SetClipSource {
+        clip,
+        src_start,
+        src_end,
+    })
+}
+
+// 페이드 핸들: frames = 엔진 샘플레이트 기준 길이 (0 = 페이드 없음)
+// curve: 0 = linear, 1 = equal-power, 2 = exponential
+#[no_mangle]
+pub extern "C" fn rust_sound_set_clip_fade_in(
+    engine: *mut Engine,
+    clip: u64,
+    frames: u64,
+    curve: u32,
+) -> bool {
+    set_clip_fade(engine, clip, frames, curve, true)
+}
+
+#[no_mangle]
+pub extern "C" fn rust_sound_set_clip_fade_out(
+    engine: *mut Engine,
+    clip: u64,
+    frames: u64,
+    curve: u32,
+) -> bool {
+    set_clip_fade(engine, clip, frames, curve, false)
+}
+
+fn set_clip_fade(engine: *mut Engine, clip: u64, frames: u64, curve: u32, is_in: bool) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let Some(curve) = FadeCurve::from_code(curve) else {
+        return false;
     };
-    if idx >= eng.track.len() || idx >= eng.track_run_time.len() {
+    let fade = Some(Fade { frames, curve });
+    let (fade_in, fade_out) = if is_in { (fade, None) } else { (None, fade) };
+    eng.submit_edit(EditCommand::SetClipFades {
+        clip,
+        fade_in,
+        fade_out,
+    })
+}
+
+// 클립 게인 (dB, MIN_CLIP_GAIN_DB..MAX_CLIP_GAIN_DB로 제한) + 극성 반전
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_set_clip_gain(
+    engine: *mut Engine,
+    clip: u64,
+    gain_db: f32,
+    invert: bool,
+) -> bool {
+    if engine.is_null() {
         return false;
     }
-    if tl_len == 0 {
+    let eng: &mut Engine = unsafe { &mut *engine };
+    if gain_db.is_nan() {
         return false;
     }
+    eng.submit_edit(EditCommand::SetClipGain {
+        clip,
+        gain_db,
+        invert,
+    })
+}
 
-    let c_str = unsafe { CStr::from_ptr(path) };
-    let path_str = c_str.to_string_lossy().into_owned();
+// 클립이 없으면 0 dB
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_clip_gain_db(engine: *const Engine, clip: u64) -> f32 {
+    if engine.is_null() {
+        return 0.0;
+    }
+    let eng = unsafe { &*engine };
+    eng.read_clip(clip, |c| c.gain_db).unwrap_or(0.0)
+}
 
-    if let Some(mx) = eng.track_run_time.get(idx) {
-        if let Ok(mut tr) = mx.lock() {
-            if tr.clips.contains_key(&tl_start) {
-                return false;
-            } // 중복 금지
-            let clip = Clip {
-                file_path: path_str,
-                src_sr: src,
-                tl_start,
-                tl_len,
-            };
-            tr.clips.insert(tl_start, clip);
-            return true;
-        }
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_clip_inverted(engine: *const Engine, clip: u64) -> bool {
+    if engine.is_null() {
+        return false;
     }
-    false
+    let eng = unsafe { &*engine };
+    eng.read_clip(clip, |c| c.invert).unwrap_or(false)
 }
 
+// 클립 반복: loop_len = 한 바퀴 길이 (엔진 샘플레이트 기준, 0 = 반복 끔)
+// loop_count = 반복 횟수 (0 = 클립 길이 tl_len 끝까지 계속)
 #[no_mangle]
-pub extern "C" fn rust_sound_move_clip_by_start(
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_set_clip_loop(
     engine: *mut Engine,
-    old_track: i32,
-    old_start: u64,
-    new_track: i32,
-    new_start: u64,
+    clip: u64,
+    loop_len: u64,
+    loop_count: u32,
 ) -> bool {
     if engine.is_null() {
         return false;
     }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    eng.submit_edit(EditCommand::SetClipLoop {
+        clip,
+        loop_len,
+        loop_count,
+    })
+}
 
+// 역재생 (트림/페이드/반복/피치/템포 방식은 그대로, 소스 구간을 끝에서부터)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_set_clip_reversed(
+    engine: *mut Engine,
+    clip: u64,
+    reversed: bool,
+) -> bool {
+    if engine.is_null() {
+        return false;
+    }
     let eng: &mut Engine = unsafe { &mut *engine };
+    eng.submit_edit(EditCommand::SetClipReversed { clip, reversed })
+}
 
-    let old_idx = match usize::try_from(old_track) {
-        Ok(n) => n,
-        Err(_) => return false,
+// 템포 처리 방식: 0 = 리샘플 (피치도 같이 변함), 1 = 타임 스트레치 (피치 유지)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_set_clip_tempo_mode(
+    engine: *mut Engine,
+    clip: u64,
+    mode: u32,
+) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let Some(mode) = TempoMode::from_code(mode) else {
+        return false;
     };
-    let new_idx = match usize::try_from(new_track) {
-        Ok(n) => n,
-        Err(_) => return false,
+    eng.submit_edit(EditCommand::SetClipTempoMode { clip, mode })
+}
+
+// 클립이 없으면 0 (리샘플)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_clip_tempo_mode(engine: *const Engine, clip: u64) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.read_clip(clip, |c| c.tempo_mode.code()).unwrap_or(0)
+}
+
+// 클립 피치: semitones 반음 + cents (합쳐서 ±MAX_CLIP_PITCH_CENTS 안으로)
+// mode: 0 = 바리스피드 (길이도 같이 변함), 1 = 길이 유지
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_set_clip_pitch(
+    engine: *mut Engine,
+    clip: u64,
+    semitones: i32,
+    cents: f32,
+    mode: u32,
+) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    if !cents.is_finite() {
+        return false;
+    }
+    let Some(mode) = PitchMode::from_code(mode) else {
+        return false;
     };
+    let total =
+        (semitones as f32 * 100.0 + cents).clamp(-MAX_CLIP_PITCH_CENTS, MAX_CLIP_PITCH_CENTS);
+    eng.submit_edit(EditCommand::SetClipPitch {
+        clip,
+        cents: total,
+        mode,
+    })
+}
 
-    if old_idx >= eng.track_run_time.len() || new_idx >= eng.track_run_time.len() {
+// 클립이 없으면 0 (반음 + cent 합계)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_clip_pitch_cents(engine: *const Engine, clip: u64) -> f32 {
+    if engine.is_null() {
+        return 0.0;
+    }
+    let eng = unsafe { &*engine };
+    eng.read_clip(clip, |c| c.pitch_cents).unwrap_or(0.0)
+}
+
+// 박 고정: 켜면 클립 위치/길이를 틱으로 들고 있다가 템포가 바뀌면 따라 움직임
+// (켤 때 시작/끝은 가장 가까운 틱으로 맞춰짐)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_set_clip_anchor(
+    engine: *mut Engine,
+    clip: u64,
+    anchored: bool,
+) -> bool {
+    if engine.is_null() {
         return false;
     }
-    if old_idx == new_idx && old_start == new_start {
-        return true;
+    let eng: &mut Engine = unsafe { &mut *engine };
+    eng.submit_edit(EditCommand::SetClipAnchor { clip, anchored })
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_clip_anchored(engine: *const Engine, clip: u64) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.read_clip(clip, |c| c.anchor.is_some()).unwrap_or(false)
+}
+
+// 클립의 타임라인 시작/길이 (지금 엔진 샘플레이트 기준 프레임), 없는 id면 0
+// 샘플레이트/템포가 바뀐 뒤 UI는 이걸로 다시 맞춤
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_clip_start(engine: *const Engine, clip: u64) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.read_clip(clip, |c| c.tl_start).unwrap_or(0)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_clip_len(engine: *const Engine, clip: u64) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.read_clip(clip, |c| c.tl_len).unwrap_or(0)
+}
+
+// 박 고정 클립의 시작/길이 (틱, TICKS_PER_BEAT = 한 박), 고정 안 된 클립이면 0
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_clip_start_ticks(engine: *const Engine, clip: u64) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.read_clip(clip, |c| c.anchor.map_or(0, |a| a.start))
+        .unwrap_or(0)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_clip_len_ticks(engine: *const Engine, clip: u64) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.read_clip(clip, |c| c.anchor.map_or(0, |a| a.len))
+        .unwrap_or(0)
+}
+
+// 처음부터 박에 고정된 클립 추가 (시작/길이는 틱), 되돌리기 한 번에 같이 사라짐
+// 반환: 새 클립 id (0 = 실패)
+#[no_mangle]
+#[allow(clippy::too_many_arguments, clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_add_clip_ticks(
+    engine: *mut Engine,
+    number: i32,
+    path: *const c_char,
+    start_ticks: u64,
+    len_ticks: u64,
+    src: u32,
+    src_start: u64,
+    src_end: u64,
+) -> u64 {
+    if engine.is_null() || len_ticks == 0 {
+        return 0;
     }
+    let (start, len) = {
+        let eng = unsafe { &*engine };
+        let grid = eng.time_grid();
+        let start = grid.frame_at_ticks(start_ticks as f64).round() as u64;
+        let end = grid
+            .frame_at_ticks(start_ticks.saturating_add(len_ticks) as f64)
+            .round() as u64;
+        eng.submit_edit(EditCommand::BeginGroup);
+        (start, end.saturating_sub(start).max(1))
+    };
+    let id = rust_sound_add_clip(engine, number, path, start, len, src, src_start, src_end);
+    let eng = unsafe { &*engine };
+    if id != 0 {
+        eng.submit_edit(EditCommand::SetClipAnchor {
+            clip: id,
+            anchored: true,
+        });
+    }
+    eng.submit_edit(EditCommand::EndGroup);
+    id
+}
 
-    // 동일 트랙: 단일 락으로 원자적 처리
-    if old_idx == new_idx {
-        if let Some(mx) = eng.track_run_time.get(old_idx) {
-            if let Ok(mut tr) = mx.lock() {
-                // 락 획득
-                let Some(mut clip) = tr.clips.remove(&old_start) else {
-                    return false;
-                }; // 이동할 클립이 없으면 실패
-                if tr.clips.contains_key(&new_start) {
-                    // 충돌 검사
-                    tr.clips.insert(old_start, clip); // 원복
-                    return false;
-                }
-                clip.tl_start = new_start;
-                tr.clips.insert(new_start, clip);
-                return true;
-            }
+// 클립 이동 (다른 트랙으로도), 같은 시작 위치에 다른 클립이 있어도 됨
+// -------------------------
+// 클립 분할/길이 조절 (적용은 편집 큐에서, 한 트랙 락 안에서 원자적으로)
+// -------------------------
+impl TrackTimeline {
+    // at에서 둘로 자름: 앞조각은 원래 id, 뒷조각은 new_id
+    // 뒷조각은 tl_offset으로 이어 읽어서 반복/역재생/템포가 있어도 소리가 그대로 이어짐
+    pub fn split_clip(&mut self, key: ClipKey, at: u64, new_id: u64) -> bool {
+        let Some(left) = self.clips.get_mut(&key) else {
+            return false;
+        };
+        let end = left.tl_start.saturating_add(left.tl_len);
+        if at <= left.tl_start || at >= end {
+            return false;
         }
-        return false;
+        let cut = at - left.tl_start;
+        let mut right = left.clone();
+        left.tl_len = cut;
+        left.fade_out = Fade::default(); //자른 자리엔 페이드 없음
+        right.id = new_id;
+        right.tl_start = at;
+        right.tl_len = end - at;
+        right.tl_offset += cut;
+        right.fade_in = Fade::default();
+        self.clips.insert((at, new_id), right);
+        true
     }
 
-    // 서로 다른 트랙: 락 순서 고정(min→max)로 데드락 방지 + 원복 보장
-    let (first, second) = if old_idx < new_idx {
-        (old_idx, new_idx)
+    // 끝 위치만 바꿈 (시작/소스 위치 그대로), 늘려도 정해 둔 소스 구간 끝까지만
+    // 반복 클립이나 파일 끝까지 쓰는 클립은 그대로 늘어남 (소스 끝 뒤는 반복 또는 무음)
+    pub fn resize_clip(&mut self, key: ClipKey, tl_len: u64, grid: &TimeGrid) -> bool {
+        if tl_len == 0 {
+            return false;
+        }
+        let Some(clip) = self.clips.get_mut(&key) else {
+            return false;
+        };
+        let max = clip.source_tl_len(grid.sr(), grid.tempo());
+        clip.tl_len = max.map_or(tl_len, |m| tl_len.min(m));
+        true
+    }
+
+    // 프레임 단위로 고친 뒤 박 고정 클립의 틱을 맞춤 (시작이 틱에 맞춰 움직이면 키도 바뀜)
+    pub fn sync_anchor(&mut self, key: ClipKey, grid: &TimeGrid) {
+        let Some(mut clip) = self.clips.remove(&key) else {
+            return;
+        };
+        clip.sync_anchor(grid);
+        self.clips.insert((clip.tl_start, clip.id), clip);
+    }
+}
+
+// 클립을 at(타임라인 프레임)에서 둘로 나눔, 반환: 뒷조각 새 id (0 = 실패)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_split_clip(engine: *mut Engine, clip: u64, at: u64) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    // 이미 적용된 클립이면 접수 전에 범위 확인
+    let inside = |c: &Clip| at > c.tl_start && at < c.tl_start.saturating_add(c.tl_len);
+    if eng.read_clip(clip, inside) == Some(false) {
+        return 0;
+    }
+    let new_id = eng.alloc_clip_id();
+    if eng.submit_edit(EditCommand::SplitClip { clip, at, new_id }) {
+        new_id
     } else {
-        (new_idx, old_idx)
-    };
-    let (mx_first, mx_second) = match (
-        eng.track_run_time.get(first),
-        eng.track_run_time.get(second),
-    ) {
-        (Some(a), Some(b)) => (a, b),
-        _ => return false,
+        0
+    }
+}
+
+// 클립 복사본을 track/start에 놓음 (설정 전부 복사), 반환: 새 id (0 = 실패)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_duplicate_clip(
+    engine: *mut Engine,
+    clip: u64,
+    track: i32,
+    start: u64,
+) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let Some(track) = eng.track_id_at(track) else {
+        return 0;
     };
+    let new_id = eng.alloc_clip_id();
+    if eng.submit_edit(EditCommand::DuplicateClip {
+        clip,
+        new_id,
+        track,
+        start,
+    }) {
+        new_id
+    } else {
+        0
+    }
+}
 
-    let g1 = mx_first.lock();
-    if g1.is_err() {
+// 클립 끝 조절: tl_len = 새 길이 (엔진 샘플레이트 기준, 0은 거부, 소스 구간보다 길면 거기까지)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_resize_clip(engine: *mut Engine, clip: u64, tl_len: u64) -> bool {
+    if engine.is_null() {
         return false;
     }
-    let mut t_first = g1.unwrap();
-    let g2 = mx_second.lock();
-    if g2.is_err() {
+    let eng: &mut Engine = unsafe { &mut *engine };
+    if tl_len == 0 {
         return false;
     }
-    let mut t_second = g2.unwrap();
+    eng.submit_edit(EditCommand::ResizeClip { clip, tl_len })
+}
 
-    let (src, dst) = if old_idx == first {
-        (&mut *t_first, &mut *t_second)
-    } else {
-        (&mut *t_second, &mut *t_first)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_move_clip(
+    engine: *mut Engine,
+    clip: u64,
+    new_track: i32,
+    new_start: u64,
+) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let Some(new_track) = eng.track_id_at(new_track) else {
+        return false;
     };
+    eng.submit_edit(EditCommand::MoveClip {
+        clip: ClipRef::Id(clip),
+        new_track,
+        new_start,
+    })
+}
 
-    let Some(mut clip) = src.clips.remove(&old_start) else {
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_delete_clip(engine: *mut Engine, clip: u64) -> bool {
+    if engine.is_null() {
         return false;
-    };
-    if dst.clips.contains_key(&new_start) {
-        src.clips.insert(old_start, clip); // 충돌 → 원복
+    }
+    let eng: &mut Engine = unsafe { &mut *engine };
+    eng.submit_edit(EditCommand::DeleteClip {
+        clip: ClipRef::Id(clip),
+    })
+}
+
+// 예전 방식: (트랙, 시작 위치)로 지정 — 같은 위치에 여럿이면 먼저 추가된 클립
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_move_clip_by_start(
+    engine: *mut Engine,
+    old_track: i32,
+    old_start: u64,
+    new_track: i32,
+    new_start: u64,
+) -> bool {
+    if engine.is_null() {
         return false;
     }
-    clip.tl_start = new_start;
-    dst.clips.insert(new_start, clip);
-    true
+
+    let eng: &mut Engine = unsafe { &mut *engine };
+
+    let (Some(old_id), Some(new_id)) = (eng.track_id_at(old_track), eng.track_id_at(new_track))
+    else {
+        return false;
+    };
+    if old_id == new_id && old_start == new_start {
+        return true;
+    }
+    eng.submit_edit(EditCommand::MoveClip {
+        clip: ClipRef::At {
+            track: old_id,
+            start: old_start,
+        },
+        new_track: new_id,
+        new_start,
+    })
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_sound_delete_clip_by_start(
     engine: *mut Engine,
     track: i32,
@@ -153,96 +664,124 @@ pub extern "C" fn rust_sound_delete_clip_by_start(
         return false;
     }
     let eng: &mut Engine = unsafe { &mut *engine };
-    let idx = match usize::try_from(track) {
-        Ok(number) => number,
-        Err(_) => return false,
-    };
-    if idx >= eng.track.len() || idx >= eng.track_run_time.len() {
+    let Some(track) = eng.track_id_at(track) else {
         return false;
-    }
-
-    if let Some(tr_mx) = eng.track_run_time.get(idx) {
-        if let Ok(mut tr) = tr_mx.lock() {
-            return tr.clips.remove(&start).is_some();
-        }
-    }
-    false
+    };
+    eng.submit_edit(EditCommand::DeleteClip {
+        clip: ClipRef::At { track, start },
+    })
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_sound_volume_update(engine: *mut Engine, volume: f32, number: i32) -> bool {
     if engine.is_null() {
         return false;
     }
     let eng: &mut Engine = unsafe { &mut *engine };
-    let idx = match usize::try_from(number) {
-        Ok(number) => number,
-        Err(_) => return false,
+    let Some(track) = eng.track_id_at(number) else {
+        return false;
     };
     let v = volume.clamp(0.0, 1.0);
-    eng.track[idx].volume = v;
-    eng.real_time_params.volume[idx].store(v.to_bits(), Ordering::Relaxed); //실시간 반영
-    true
+    eng.set_mixer(track, MixerParam::Volume(v))
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_sound_mute_update(engine: *mut Engine, mute: bool, number: i32) -> bool {
     if engine.is_null() {
         return false;
     }
     let eng: &mut Engine = unsafe { &mut *engine };
-    let idx = match usize::try_from(number) {
-        Ok(number) => number,
-        Err(_) => return false,
+    let Some(track) = eng.track_id_at(number) else {
+        return false;
     };
-    if idx >= eng.track.len() {
+    eng.set_mixer(track, MixerParam::Mute(mute))
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_pan_update(engine: *mut Engine, pan: f32, number: i32) -> bool {
+    if engine.is_null() {
         return false;
     }
-    eng.track[idx].muted = mute;
-    eng.real_time_params.muted[idx].store(mute, Ordering::Relaxed); //실시간 반영
-    true
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let Some(track) = eng.track_id_at(number) else {
+        return false;
+    };
+    let p = if pan.is_finite() {
+        pan.clamp(-1.0, 1.0)
+    } else {
+        0.0
+    };
+    eng.set_mixer(track, MixerParam::Pan(p))
 }
 
+// 출력 채널 지정: channel < 0 이면 메인(레이아웃 패닝), 아니면 channel/channel+1 로 직접
 #[no_mangle]
-pub extern "C" fn rust_sound_pan_update(engine: *mut Engine, pan: f32, number: i32) -> bool {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_output_update(engine: *mut Engine, channel: i32, number: i32) -> bool {
     if engine.is_null() {
         return false;
     }
     let eng: &mut Engine = unsafe { &mut *engine };
-    let idx = match usize::try_from(number) {
-        Ok(number) => number,
-        Err(_) => return false,
+    let Some(track) = eng.track_id_at(number) else {
+        return false;
     };
-    if idx >= eng.track.len() {
+    let channel = if channel < 0 {
+        -1
+    } else if channel as usize >= MAX_OUT_CHANNELS {
+        return false;
+    } else {
+        channel
+    };
+    eng.set_mixer(track, MixerParam::Route(channel))
+}
+
+// 서라운드 레이아웃에서 트랙 방향 (도, 0 = 정면, 90 = 오른쪽)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_sound_azimuth_update(
+    engine: *mut Engine,
+    azimuth: f32,
+    number: i32,
+) -> bool {
+    if engine.is_null() {
         return false;
     }
-    let p = if pan.is_finite() {
-        pan.clamp(-1.0, 1.0)
+    let eng: &mut Engine = unsafe { &mut *engine };
+    let Some(track) = eng.track_id_at(number) else {
+        return false;
+    };
+    let a = if azimuth.is_finite() {
+        (azimuth + 180.0).rem_euclid(360.0) - 180.0
     } else {
         0.0
     };
-    eng.track[idx].pan = p;
-    eng.real_time_params.pan[idx].store(p.to_bits(), Ordering::Relaxed); //실시간 반영
-    true
+    eng.set_mixer(track, MixerParam::Azimuth(a))
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_sound_bpm_update(engine: *mut Engine, bpm: f32) -> bool {
-     if engine.is_null() { return false; }
+    if engine.is_null() {
+        return false;
+    }
     let eng: &mut Engine = unsafe { &mut *engine };
 
-    let b = bpm.clamp(20.0, 300.0);
-    let cur = f32::from_bits(eng.real_time_params.bpm.load(Ordering::Relaxed));
-    if (cur - b).abs() < 0.0001 { return true; }
+    let b = bpm.clamp(MIN_BPM, MAX_BPM);
+    let cur = eng.tempo().base_bpm();
+    if (cur - b).abs() < 0.0001 {
+        return true;
+    }
 
-    // 방법 A) pending 플래그 써서 rebuffer에서 적용
-    eng.pending_bpm.store(b.to_bits(), Ordering::Relaxed);
-    eng.has_pending_bpm.store(true, Ordering::Release);
-    eng.rebuffer_current();
+    eng.record_bpm_change(cur);
+    eng.set_bpm(b);
     true
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_transport_pos(engine: *mut Engine) -> u64 {
     if engine.is_null() {
         return 0;
@@ -252,6 +791,7 @@ pub extern "C" fn rust_transport_pos(engine: *mut Engine) -> u64 {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_transport_sr(engine: *mut Engine) -> u32 {
     if engine.is_null() {
         return 48000;
@@ -261,6 +801,7 @@ pub extern "C" fn rust_transport_sr(engine: *mut Engine) -> u32 {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_transport_is_playing(engine: *const Engine) -> bool {
     if engine.is_null() {
         return false;
@@ -270,6 +811,7 @@ pub extern "C" fn rust_transport_is_playing(engine: *const Engine) -> bool {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_audio_params_out_sr(engine: *mut Engine) -> u32 {
     if engine.is_null() {
         return 0;
@@ -284,15 +826,77 @@ pub extern "C" fn rust_audio_params_out_bs(_engine: *mut Engine) -> u32 {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_engine_set_sr(engine: *mut Engine, sr: u32) {
     if engine.is_null() {
         return;
     }
     let eng = unsafe { &*engine };
-    eng.play_time_manager.set_sr(sr);
+    // 재생 위치/클립 위치를 새 레이트로 환산하고 리버퍼 (재생 중이면 이어서 재생)
+    eng.change_sample_rate(sr);
+}
+
+// JUCE 장치 콜백 시작/정지 알림 (도는 동안은 엔진 cpal 출력을 못 엶)
+// 엔진 cpal 출력이 이미 돌고 있으면 켜기를 거부
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_engine_set_host_render(engine: *mut Engine, on: bool) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.set_host_render(on)
+}
+
+// 출력 레이아웃: 0 = 스테레오, 1 = 쿼드, 2 = 5.1
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_engine_set_output_layout(engine: *mut Engine, layout: u32) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    let Some(layout) = SpeakerLayout::from_code(layout) else {
+        return false;
+    };
+    eng.set_output_layout(layout);
+    true
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_engine_output_channels(engine: *const Engine) -> u32 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.output_layout().channels() as u32
+}
+
+// 트랙 링버퍼 지연 목표 (ms) → 재생 중이면 바로 리버퍼
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_engine_set_ring_latency_ms(engine: *mut Engine, ms: u32) -> bool {
+    if engine.is_null() {
+        return false;
+    }
+    let eng = unsafe { &*engine };
+    eng.set_ring_latency_ms(ms);
+    true
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_engine_ring_frames(engine: *const Engine) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let eng = unsafe { &*engine };
+    eng.ring_frames() as u64
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_project_length_frames(engine: *mut Engine) -> u64 {
     if engine.is_null() {
         return 0;
@@ -302,28 +906,149 @@ pub extern "C" fn rust_project_length_frames(engine: *mut Engine) -> u64 {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_project_length_seconds(engine: *const Engine) -> f64 {
-    if engine.is_null() { return 0.0; }
+    if engine.is_null() {
+        return 0.0;
+    }
     let eng = unsafe { &*engine };
     eng.project_length_seconds()
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_metrics_get_xrun_callbacks(eng: *mut Engine) -> u64 {
-    if eng.is_null() { return 0; }
+    if eng.is_null() {
+        return 0;
+    }
     unsafe { (&*eng).underrun_callbacks.load(Ordering::Relaxed) }
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_metrics_get_xrun_zero_samples(eng: *mut Engine) -> u64 {
-    if eng.is_null() { return 0; }
+    if eng.is_null() {
+        return 0;
+    }
     unsafe { (&*eng).underrun_samples.load(Ordering::Relaxed) }
 }
 
+// 재생 중 접수됐다가 적용 시점에 대상이 없어 거부된 편집 수
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rust_metrics_get_rejected_edits(eng: *mut Engine) -> u64 {
+    if eng.is_null() {
+        return 0;
+    }
+    unsafe { (&*eng).rejected_edits() }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn rust_metrics_reset(eng: *mut Engine) {
-    if eng.is_null() { return; }
+    if eng.is_null() {
+        return;
+    }
     let e = unsafe { &*eng };
     e.underrun_callbacks.store(0, Ordering::Relaxed);
     e.underrun_samples.store(0, Ordering::Relaxed);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    // 48k 엔진에 소스 0..src_end (0 = 파일 끝까지) 클립 하나
+    fn engine_with_clip(tl_start: u64, tl_len: u64, src_sr: u32, src_end: u64) -> (Engine, u64) {
+        let mut eng = Engine::with_track_count(1).unwrap();
+        let path = CString::new("missing.wav").unwrap();
+        let id = rust_sound_add_clip(
+            &mut eng,
+            0,
+            path.as_ptr(),
+            tl_start,
+            tl_len,
+            src_sr,
+            0,
+            src_end,
+        );
+        assert_ne!(id, 0);
+        (eng, id)
+    }
+
+    fn span(eng: &Engine, id: u64) -> (u64, u64, u64) {
+        eng.read_clip(id, |c| (c.tl_start, c.tl_len, c.tl_offset))
+            .unwrap()
+    }
+
+    #[test]
+    fn split_rejects_clip_edges() {
+        let (mut eng, id) = engine_with_clip(1_000, 4_000, 48_000, 0);
+        assert_eq!(rust_sound_split_clip(&mut eng, id, 1_000), 0);
+        assert_eq!(rust_sound_split_clip(&mut eng, id, 5_000), 0);
+        assert_eq!(rust_sound_split_clip(&mut eng, id, 999), 0);
+        assert_eq!(span(&eng, id), (1_000, 4_000, 0));
+        // 거부된 분할은 기록도 안 남음 (추가 한 단계만)
+        assert!(eng.undo());
+        assert!(!eng.can_undo());
+    }
+
+    #[test]
+    fn split_halves_continue_the_source() {
+        let (mut eng, id) = engine_with_clip(1_000, 4_000, 48_000, 0);
+        let right = rust_sound_split_clip(&mut eng, id, 2_500);
+        assert_ne!(right, 0);
+        let (l_start, l_len, l_off) = span(&eng, id);
+        let (r_start, r_len, r_off) = span(&eng, right);
+        assert_eq!((l_start, l_off), (1_000, 0));
+        assert_eq!(r_start, l_start + l_len);
+        assert_eq!(r_off, l_off + l_len);
+        assert_eq!(l_len + r_len, 4_000);
+
+        // 뒷조각을 다시 나눠도 오프셋이 이어짐
+        let third = rust_sound_split_clip(&mut eng, right, 4_000);
+        let (_, r_len, _) = span(&eng, right);
+        assert_eq!(span(&eng, third), (4_000, 1_000, r_off + r_len));
+    }
+
+    #[test]
+    fn resize_is_clamped_to_source_range() {
+        // 96k 소스 9_600 frames = 48k 타임라인 4_800 frames
+        let (mut eng, id) = engine_with_clip(0, 2_000, 96_000, 9_600);
+        assert!(rust_sound_resize_clip(&mut eng, id, 10_000));
+        assert_eq!(span(&eng, id).1, 4_800);
+        assert!(rust_sound_resize_clip(&mut eng, id, 3_000));
+        assert_eq!(span(&eng, id).1, 3_000);
+        assert!(!rust_sound_resize_clip(&mut eng, id, 0));
+
+        // 뒷조각은 잘려 나간 앞부분만큼 덜 늘어남
+        let right = rust_sound_split_clip(&mut eng, id, 1_000);
+        assert!(rust_sound_resize_clip(&mut eng, right, 10_000));
+        assert_eq!(span(&eng, right), (1_000, 3_800, 1_000));
+
+        // 파일 끝까지 쓰는 클립은 길이를 모르므로 그대로
+        let (mut eng, id) = engine_with_clip(0, 2_000, 48_000, 0);
+        assert!(rust_sound_resize_clip(&mut eng, id, 100_000));
+        assert_eq!(span(&eng, id).1, 100_000);
+    }
+
+    #[test]
+    fn duplicate_gets_fresh_id() {
+        let (mut eng, id) = engine_with_clip(1_000, 4_000, 48_000, 0);
+        let gain = |c: &Clip| c.gain_db;
+        assert!(rust_sound_set_clip_gain(&mut eng, id, -6.0, false));
+        let copy = rust_sound_duplicate_clip(&mut eng, id, 0, 8_000);
+        assert_ne!(copy, 0);
+        assert_ne!(copy, id);
+        assert_eq!(eng.read_clip(copy, |c| c.id), Some(copy));
+        assert_eq!(span(&eng, copy), (8_000, 4_000, 0));
+        assert_eq!(eng.read_clip(copy, gain), Some(-6.0));
+        assert_eq!(span(&eng, id), (1_000, 4_000, 0));
+
+        // 같은 자리에 또 복제해도 서로 다른 클립
+        let again = rust_sound_duplicate_clip(&mut eng, id, 0, 8_000);
+        assert!(again != 0 && again != copy);
+        assert!(eng.read_clip(copy, |_| ()).is_some());
+    }
+}
@@ -1,24 +1,138 @@
-pub const CHANNELS: usize = 2;
+pub const CHANNELS: usize = 2; // 트랙 링버퍼/디코드 채널 (항상 스테레오)
+pub const MAX_OUT_CHANNELS: usize = 32; // 출력 채널 수 상한
 #[inline]
 pub fn slots(frames: usize) -> usize {
     frames * CHANNELS
 } // frames → samples
 
-// 링버퍼·FIFO 용량 (전부 frames 단위)
-pub const RB1_FRAMES: usize = 16_777_216; // 트랙(1차) 링버퍼 용량
+// 트랙(1차) 링버퍼 용량: 엔진마다 지연 목표(ms)와 현재 샘플레이트로 정함
+pub const DEFAULT_RING_MS: u32 = 500;
+pub const MAX_RING_MS: u32 = 10_000;
+pub const MIN_RING_FRAMES: usize = HIGH_FRAMES + LOW_FRAMES;
+// old_sr 기준 프레임 → new_sr 기준 프레임 (반올림)
+#[inline]
+pub fn rescale_frames(frames: u64, old_sr: u32, new_sr: u32) -> u64 {
+    if old_sr == 0 {
+        return frames;
+    }
+    let v = (frames as u128 * new_sr as u128 + old_sr as u128 / 2) / old_sr as u128;
+    v.min(u64::MAX as u128) as u64
+}
+
+#[inline]
+pub fn ring_frames_for(latency_ms: u32, sr: u32) -> usize {
+    let frames = latency_ms.min(MAX_RING_MS) as u64 * sr as u64 / 1000;
+    (frames as usize).max(MIN_RING_FRAMES)
+} // ms → frames
 
 // 디코드/복제 청크 (frames)
 pub const CHUNK_DECODE: usize = 65_536; // 디코더 워커가 한 번에 밀어넣는 크기
 pub const CHUNK_COPY: usize = 8_192; // 복제 스레드 보간 출력 단위
+pub const CHUNK_OFFLINE: usize = 4_096; // 오프라인 렌더 블록 크기
+pub const CHUNK_REVERSE: usize = 4_096; // 역재생 한 번에 거꾸로 읽는 출력 frames
+pub const REVERSE_WINDOW: usize = CHUNK_REVERSE * 8; // 역재생 때 한 번 시킹해서 디코드해 두는 소스 frames
+pub const CHUNK_STRETCH: usize = 4_096; // 타임 스트레치 한 번에 만드는 출력 frames
+
+// 타임 스트레치 (WSOLA)
+pub const STRETCH_GRAIN: usize = 2_048; // grain 길이 (출력 frames, 48k에서 약 43ms)
+pub const STRETCH_SEEK: usize = 384; // grain 위치를 옮겨 볼 범위 ± (소스 frames)
+pub const STRETCH_CORR_STRIDE: usize = 4; // 유사도 계산 때 건너뛰는 간격
 
 // 전역 워터마크 (frames) — 히스테리시스
 pub const HIGH_FRAMES: usize = 12288;
 pub const LOW_FRAMES: usize = 4096;
+// 링 용량별 (low, high): low 밑으로 떨어지면 채우기 시작, high까지 채우고 멈춤
+// play/seek 직후 동기 예열도 high까지만
+#[inline]
+pub fn watermarks(ring_frames: usize) -> (usize, usize) {
+    let high = ring_frames.saturating_sub(LOW_FRAMES).max(HIGH_FRAMES);
+    (LOW_FRAMES.max(high / 2), high)
+}
 
+// 클립 게인 (dB) 범위, 아래 끝은 사실상 무음
+pub const MIN_CLIP_GAIN_DB: f32 = -96.0;
+pub const MAX_CLIP_GAIN_DB: f32 = 24.0;
+#[inline]
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+} // dB → 선형 배율
 
-// seek 직후 동기 예열 크기 (frames)
-pub const PREFILL_ON_START: usize = RB1_FRAMES - 65_536;
-pub const PREFILL_ON_SEEK: usize = RB1_FRAMES / 2; // ≈ 170ms
+// 클립 피치 (cent, 100 = 반음) 범위 ± 두 옥타브
+pub const MAX_CLIP_PITCH_CENTS: f32 = 2_400.0;
+#[inline]
+pub fn cents_to_ratio(cents: f32) -> f32 {
+    2f32.powf(cents / 1200.0)
+} // cent → 주파수 배율
+
+pub const MAX_TRACKS: usize = 64; // 엔진 하나가 가질 수 있는 최대 트랙 수
+pub const MAX_CLIP_VOICES: usize = 4; // 트랙 하나에서 동시에 겹쳐 재생하는 클립 수 상한
+pub const RENDER_MSG_CAPACITY: usize = 256; // 컨트롤 → 렌더 메시지 큐 길이
+// 렌더 → 컨트롤 해제 큐: 메시지 하나당 쓰레기는 최대 1개, 그 밖엔 끝난 패드 1개 + 리버퍼 2개뿐
+// (컨트롤이 보낼 때마다 먼저 비우므로 이 이상 쌓이지 않음)
+pub const RENDER_TRASH_CAPACITY: usize = RENDER_MSG_CAPACITY + 4;
+pub const EDIT_QUEUE_CAPACITY: usize = 1_024; // 타임라인/믹서 편집 명령 큐 길이
+pub const MAX_UNDO_STEPS: usize = 256; // 되돌리기 기록 단계 수 상한 (넘으면 오래된 것부터 버림)
 
 pub const MAX_BUDGET: usize = HIGH_FRAMES * 6;
 pub const BASE_BPM: f32 = 60.0;
+pub const MIN_BPM: f32 = 20.0;
+pub const MAX_BPM: f32 = 300.0;
+pub const MIN_TEMPO_RATIO: f32 = 0.25; // 재생 속도 배율 범위 (bpm / BASE_BPM)
+pub const MAX_TEMPO_RATIO: f32 = 4.0;
+pub const TICKS_PER_BEAT: u32 = 960; // 박 하나를 나누는 틱 수 (마디:박:틱 표시/스냅용)
+pub const MAX_METER_NUM: u32 = 32; // 박자표 분자 상한
+pub const MAX_METER_DEN: u32 = 32; // 박자표 분모 상한 (2의 거듭제곱만)
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATES: [u32; 6] = [8_000, 44_100, 48_000, 96_000, 192_000, 384_000];
+
+    #[test]
+    fn rescale_rounds_to_nearest_frame() {
+        assert_eq!(rescale_frames(48_000, 48_000, 44_100), 44_100);
+        assert_eq!(rescale_frames(1_000, 44_100, 48_000), 1_088); // 1088.43
+        assert_eq!(rescale_frames(1_001, 48_000, 44_100), 920); // 919.67
+        assert_eq!(rescale_frames(7, 0, 44_100), 7); // 옛 레이트를 모르면 그대로
+        assert_eq!(rescale_frames(u64::MAX, 44_100, 48_000), u64::MAX);
+        // 44.1k → 48k → 44.1k 왕복은 제자리 (늘리는 쪽이 먼저면 반올림 오차가 없음)
+        for f in [0, 1, 441, 12_345, 44_100 * 3_600] {
+            let up = rescale_frames(f, 44_100, 48_000);
+            assert_eq!(rescale_frames(up, 48_000, 44_100), f);
+        }
+    }
+
+    #[test]
+    fn ring_frames_at_limits() {
+        // 지연 목표가 짧거나 레이트가 낮아도 워터마크가 들어갈 만큼은 잡음
+        assert_eq!(ring_frames_for(0, 48_000), MIN_RING_FRAMES);
+        assert_eq!(ring_frames_for(DEFAULT_RING_MS, 8_000), MIN_RING_FRAMES);
+        assert_eq!(ring_frames_for(DEFAULT_RING_MS, 48_000), 24_000);
+        // 위쪽은 MAX_RING_MS에서 자름 (레이트가 높아도 넘치지 않음)
+        assert_eq!(ring_frames_for(MAX_RING_MS, 384_000), 3_840_000);
+        assert_eq!(ring_frames_for(u32::MAX, 384_000), 3_840_000);
+        for sr in RATES {
+            assert!(ring_frames_for(1, sr) >= MIN_RING_FRAMES);
+            let max = ring_frames_for(MAX_RING_MS, sr);
+            assert_eq!(ring_frames_for(u32::MAX, sr), max);
+        }
+    }
+
+    #[test]
+    fn watermarks_fit_ring() {
+        for sr in RATES {
+            for ms in [0, 1, DEFAULT_RING_MS, MAX_RING_MS] {
+                let ring = ring_frames_for(ms, sr);
+                let (low, high) = watermarks(ring);
+                assert!(LOW_FRAMES <= low && low < high, "{sr} {ms}");
+                assert!(high <= ring, "{sr} {ms}");
+                // 가장 작은 링이 아니면 high 위로 한 블록(LOW_FRAMES) 여유
+                let room = ring - high;
+                assert!(room >= LOW_FRAMES || high == HIGH_FRAMES, "{sr} {ms}");
+            }
+        }
+        assert_eq!(watermarks(MIN_RING_FRAMES), (HIGH_FRAMES / 2, HIGH_FRAMES));
+        assert_eq!(watermarks(3_840_000), (1_917_952, 3_835_904));
+    }
+}
@@ -15,18 +15,8 @@
 //==============================================================================
 AudioEngine::AudioEngine()
 {
-    TrackDatas* rust_track_0 =rust_audio_track_new();
-    TrackDatas* rust_track_1 = rust_audio_track_new();
-    TrackDatas* rust_track_2 = rust_audio_track_new();
-    TrackDatas* rust_track_3 = rust_audio_track_new();
-    Engine* raw = rust_audio_engine_new(rust_track_0, rust_track_1, rust_track_2, rust_track_3);
-    if (!raw) {
-        rust_audio_track_free(rust_track_0);
-        rust_audio_track_free(rust_track_1);
-        rust_audio_track_free(rust_track_2);
-        rust_audio_track_free(rust_track_3);
-        return;
-    }
+    Engine* raw = rust_audio_engine_new_with_tracks((uint32_t)MainTrack::laneCount);
+    if (!raw) return;
     eng.reset(raw);
 
     auto renderFromRust = [this](float* inter, size_t frames, int ch)->size_t { // 0. Lamda callback fun output
@@ -35,7 +25,16 @@ AudioEngine::AudioEngine()
         };
     host_ = std::make_unique<AudioHostController>(renderFromRust); //create obj audio is Lama callback fun input
     host_->onAboutToStart = [this] (double sr, int,int) { //output onAboutToStart
-        if (eng) rust_engine_set_sr(eng.get(), (uint32_t)sr);
+        if (!eng) return;
+        rust_engine_set_sr(eng.get(), (uint32_t)sr);
+        // 이 콜백이 렌더를 맡는 동안 엔진 자체 cpal 출력은 못 열게 표시
+        if (!rust_engine_set_host_render(eng.get(), true)) DBG("[host_render] engine output already running");
+        // 엔진 쪽 클립 프레임이 새 레이트로 바뀌었으니 UI 클립도 다시 맞추게 알림
+        juce::Component::SafePointer<AudioEngine> safe(this);
+        juce::MessageManager::callAsync([safe] { if (safe && safe->onSampleRateChanged) safe->onSampleRateChanged(); });
+        };
+    host_->onStopped = [this] {
+        if (eng) rust_engine_set_host_render(eng.get(), false);
         };
     host_->start(); //start Just App open one App delete is stop
 }
@@ -71,36 +70,57 @@ void AudioEngine::rust_start_sound(bool bstart)
 
 
 
-bool AudioEngine::rust_file_update(int32_t number, const char* path, uint64_t tl_start, uint64_t tl_len, uint32_t src)
+uint64_t AudioEngine::rust_file_update(int32_t number, const char* path, uint64_t tl_start, uint64_t tl_len, uint32_t src,
+                                       uint64_t src_start, uint64_t src_end)
+{
+    return rust_sound_add_clip(eng.get(), number, path, tl_start, tl_len, src, src_start, src_end);
+}
+
+bool AudioEngine::rust_file_move(uint64_t clip, int32_t new_track, uint64_t new_start)
+{
+    return rust_sound_move_clip(eng.get(), clip, new_track, new_start);
+}
+
+bool AudioEngine::rust_file_delet(uint64_t clip)
+{
+    return rust_sound_delete_clip(eng.get(), clip);
+}
+
+bool AudioEngine::rust_file_range(uint64_t clip, uint64_t& start, uint64_t& len)
 {
-    return rust_sound_add_clip(eng.get(), number, path, tl_start, tl_len, src);
+    if (!eng || clip == 0) { return false; }
+    len = rust_sound_clip_len(eng.get(), clip);
+    if (len == 0) { return false; }
+    start = rust_sound_clip_start(eng.get(), clip);
+    return true;
 }
 
-bool AudioEngine::rust_file_move(int32_t old_track, uint64_t old_start, int32_t new_track, uint64_t new_start)
+int AudioEngine::rust_get_track_count()
 {
-    return rust_sound_move_clip_by_start(eng.get(), old_track, old_start, new_track, new_start);
+    return eng ? (int)rust_track_count(eng.get()) : 0;
 }
 
-bool AudioEngine::rust_file_delet(int32_t track, uint64_t start)
+bool AudioEngine::rust_track_clear(int tracknum)
 {
-    return rust_sound_delete_clip_by_start(eng.get(), track, start);
+    // 트랙과 믹서 값은 그대로, 클립만 한 번에 지움 (되돌리기 한 단계)
+    return rust_track_clear_clips(eng.get(), tracknum);
 }
 
 bool AudioEngine::rust_volume_update(float volume, int tracknum)
 {
-    if (tracknum < 0 || tracknum >= 4) { return false; }
+    if (tracknum < 0 || tracknum >= rust_get_track_count()) { return false; }
     return rust_sound_volume_update(eng.get(), volume, tracknum);
 }
 
 bool AudioEngine::rust_mute_update(bool muted, int tracknum)
 {
-    if (tracknum < 0 || tracknum >= 4) { return false; }
+    if (tracknum < 0 || tracknum >= rust_get_track_count()) { return false; }
     return rust_sound_mute_update(eng.get(), muted, tracknum);
 }
 
 bool AudioEngine::rust_pan_update(float pan, int tracknum)
 {
-    if (tracknum < 0 || tracknum >= 4) { return false; }
+    if (tracknum < 0 || tracknum >= rust_get_track_count()) { return false; }
     return rust_sound_pan_update(eng.get(), pan, tracknum);
 }
 
@@ -109,6 +129,36 @@ bool AudioEngine::rust_bpm_update(float bpm)
     return rust_sound_bpm_update(eng.get(),bpm);
 }
 
+bool AudioEngine::rust_group_begin()
+{
+    return rust_edit_begin_group(eng.get());
+}
+
+bool AudioEngine::rust_group_end()
+{
+    return rust_edit_end_group(eng.get());
+}
+
+uint32_t AudioEngine::rust_meter_num()
+{
+    return rust_time_signature_num(eng.get());
+}
+
+uint32_t AudioEngine::rust_bar_at(uint64_t frame)
+{
+    return rust_time_bar_at(eng.get(), frame);
+}
+
+uint64_t AudioEngine::rust_bbt_to_frames(uint32_t bar, uint32_t beat, uint32_t tick)
+{
+    return rust_time_bbt_to_frames(eng.get(), bar, beat, tick);
+}
+
+uint64_t AudioEngine::rust_snap(uint64_t frame, uint32_t division)
+{
+    return rust_time_snap(eng.get(), frame, division);
+}
+
 uint64_t AudioEngine::rust_get_pos()
 {
     return rust_transport_pos(eng.get());
@@ -171,66 +221,62 @@ void AudioEngine::rust_save_wav()
         .getChildFile("Ryuicni.wav");
     outFile.deleteFile();
 
-    const bool     wasPlaying = rust_get_is_playing();
-    const uint64_t prevPos = rust_get_pos();
-    if (host_) host_->stop();
+    if (!eng || !host_) return;
+
+    // 플러그인을 오프라인으로 돌리는 동안 라이브 콜백이 같은 체인을 건드리지 않게 멈춤
+    // (엔진 재생 위치/상태는 그대로, 소스는 엔진 오프라인 렌더에서 블록씩 가져옴)
+    host_->stop();
 
-    const uint32_t sr = juce::jmax<uint32_t>(44100u, rust_get_out_sr());
-    const uint32_t block = juce::jmax<uint32_t>(256u, rust_get_out_bs());
-    const uint64_t songFrames = rust_project_length_frames(eng.get());
+    const uint32_t sr = juce::jmax<uint32_t>(1u, rust_get_sr());
+    const uint32_t block = juce::jlimit<uint32_t>(256u, 4096u, rust_get_out_bs());
 
-    if (!host_ || !host_->prepareForOffline((double)sr, (int)block)) {
+    if (!host_->prepareForOffline((double)sr, (int)block)) {
         DBG("[Export] offline prepare failed");
-        if (host_) host_->start();
+        host_->start();
         return;
     }
     const int latency = juce::jmax(0, host_->getTotalLatencySamples());
-    const uint64_t tailFrames = (uint64_t)latency; 
+    // 플러그인 지연만큼 꼬리를 더 렌더하고 앞에서 그만큼 잘라냄
+    const uint64_t endFrames = rust_project_length_frames(eng.get()) + (uint64_t)latency;
 
-    rust_engine_set_sr(eng.get(), sr);
-    rust_set_play_time(0);
-    rust_start_sound(true);
+    auto finish = [this] {
+        host_->releaseOffline();
+        host_->start();
+    };
 
- 
     juce::WavAudioFormat wav;
     std::unique_ptr<juce::FileOutputStream> fos(outFile.createOutputStream());
     if (!fos || !fos->openedOk()) {
         DBG("[Export] cannot open: " + outFile.getFullPathName());
-        rust_start_sound(false);
-        rust_set_play_time(prevPos);
-        host_->releaseOffline();
-        if (wasPlaying) rust_start_sound(true);
-        if (host_) host_->start();
+        finish();
         return;
     }
     std::unique_ptr<juce::AudioFormatWriter> writer(
         wav.createWriterFor(fos.release(), (double)sr, 2, 24, {}, 0));
     if (!writer) {
         DBG("[Export] createWriterFor failed");
-        rust_start_sound(false);
-        rust_set_play_time(prevPos);
-        host_->releaseOffline();
-        if (wasPlaying) rust_start_sound(true);
-        if (host_) host_->start();
+        finish();
         return;
     }
 
-    std::vector<float> inter(block * 2, 0.0f);       
-    juce::AudioBuffer<float> buf(2, (int)block); 
-    juce::MidiBuffer midi;
+    // 체인이 스테레오 고정이므로 스테레오(0)로 렌더
+    OfflineRender* render = endFrames > 0 ? rust_offline_render_begin(eng.get(), 0, endFrames, 0) : nullptr;
+    if (!render) {
+        DBG("[Export] render failed");
+        writer.reset();
+        finish();
+        return;
+    }
 
+    std::vector<float> inter(block * 2, 0.0f);
+    juce::AudioBuffer<float> buf(2, (int)block);
+    juce::MidiBuffer midi;
     int headLeft = latency;
 
-
-    uint64_t rendered = 0;
-    const uint64_t targetFrames = songFrames + tailFrames;
-
-    while (rendered < targetFrames)
+    for (;;)
     {
-        const uint32_t todo = (uint32_t)juce::jmin<uint64_t>(block, targetFrames - rendered);
-
-        size_t got = rust_render_interleaved(eng.get(), inter.data(), (size_t)todo, 2);
-        if (got == 0) { juce::Thread::sleep(1); continue; }
+        const size_t got = rust_offline_render_next(render, inter.data(), (size_t)block);
+        if (got == 0) break;
 
         buf.clear();
         float* L = buf.getWritePointer(0);
@@ -243,7 +289,6 @@ void AudioEngine::rust_save_wav()
         midi.clear();
         host_->processChainOffline(buf, midi);
 
-
         int writeOffset = 0;
         int writeCount = (int)got;
         if (headLeft > 0) {
@@ -255,17 +300,11 @@ void AudioEngine::rust_save_wav()
 
         if (writeCount > 0)
             writer->writeFromAudioSampleBuffer(buf, writeOffset, writeCount);
-
-        rendered += got;
     }
 
+    rust_offline_render_free(render);
     writer.reset();
-    rust_start_sound(false);
-    rust_set_play_time(prevPos);
-    host_->releaseOffline();
-
-    if (wasPlaying) rust_start_sound(true);
-    if (host_) host_->start();
+    finish();
 
     DBG("[Export] DONE -> " + outFile.getFullPathName());
 }
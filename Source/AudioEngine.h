@@ -19,30 +19,107 @@ extern "C"
 {
     struct Engine;
     struct TrackDatas;
+    struct OfflineRender;
     TrackDatas* rust_audio_track_new();
     void rust_audio_track_free(TrackDatas* track);
 
     Engine* rust_audio_engine_new(TrackDatas* track0, TrackDatas* track1, TrackDatas* track2, TrackDatas* track3);
+    Engine* rust_audio_engine_new_with_tracks(uint32_t count);
+    Engine* rust_audio_engine_new_empty();
     void rust_audio_engine_free(Engine* engine);
 
     bool rust_sound_play(Engine* engine);
     bool rust_sound_stop(Engine* engine);
 
-    bool rust_sound_add_clip(Engine* engine, int32_t number, const char* path, uint64_t tl_start, uint64_t tl_len, uint32_t src);
+    uint32_t rust_track_count(Engine* engine);
+    int32_t rust_track_add(Engine* engine);
+    bool rust_track_insert(Engine* engine, int32_t number);
+    bool rust_track_remove(Engine* engine, int32_t number);
+    bool rust_track_clear_clips(Engine* engine, int32_t number);
+    bool rust_track_move(Engine* engine, int32_t from, int32_t to);
+
+    uint64_t rust_sound_add_clip(Engine* engine, int32_t number, const char* path, uint64_t tl_start, uint64_t tl_len, uint32_t src, uint64_t src_start, uint64_t src_end);
+    bool rust_sound_set_clip_source(Engine* engine, uint64_t clip, uint64_t src_start, uint64_t src_end);
+    bool rust_sound_set_clip_fade_in(Engine* engine, uint64_t clip, uint64_t frames, uint32_t curve);
+    bool rust_sound_set_clip_fade_out(Engine* engine, uint64_t clip, uint64_t frames, uint32_t curve);
+    bool rust_sound_set_clip_gain(Engine* engine, uint64_t clip, float gain_db, bool invert);
+    float rust_sound_clip_gain_db(Engine* engine, uint64_t clip);
+    bool rust_sound_clip_inverted(Engine* engine, uint64_t clip);
+    bool rust_sound_set_clip_loop(Engine* engine, uint64_t clip, uint64_t loop_len, uint32_t loop_count);
+    bool rust_sound_set_clip_reversed(Engine* engine, uint64_t clip, bool reversed);
+    bool rust_sound_set_clip_tempo_mode(Engine* engine, uint64_t clip, uint32_t mode);
+    uint32_t rust_sound_clip_tempo_mode(const Engine* engine, uint64_t clip);
+    bool rust_sound_set_clip_pitch(Engine* engine, uint64_t clip, int32_t semitones, float cents, uint32_t mode);
+    float rust_sound_clip_pitch_cents(const Engine* engine, uint64_t clip);
+    bool rust_sound_set_clip_anchor(Engine* engine, uint64_t clip, bool anchored);
+    uint64_t rust_sound_clip_start(const Engine* engine, uint64_t clip);
+    uint64_t rust_sound_clip_len(const Engine* engine, uint64_t clip);
+    bool rust_sound_clip_anchored(const Engine* engine, uint64_t clip);
+    uint64_t rust_sound_clip_start_ticks(const Engine* engine, uint64_t clip);
+    uint64_t rust_sound_clip_len_ticks(const Engine* engine, uint64_t clip);
+    uint64_t rust_sound_add_clip_ticks(Engine* engine, int32_t number, const char* path, uint64_t start_ticks, uint64_t len_ticks, uint32_t src, uint64_t src_start, uint64_t src_end);
+    bool rust_sound_move_clip(Engine* engine, uint64_t clip, int32_t new_track, uint64_t new_start);
+    bool rust_sound_delete_clip(Engine* engine, uint64_t clip);
+    uint64_t rust_sound_split_clip(Engine* engine, uint64_t clip, uint64_t at);
+    uint64_t rust_sound_duplicate_clip(Engine* engine, uint64_t clip, int32_t track, uint64_t start);
+    bool rust_sound_resize_clip(Engine* engine, uint64_t clip, uint64_t tl_len);
     bool rust_sound_move_clip_by_start(Engine* engine, int32_t old_track, uint64_t old_start, int32_t new_track, uint64_t new_start);
     bool rust_sound_delete_clip_by_start(Engine* engine, int32_t track, uint64_t start);
     bool rust_sound_volume_update(Engine* engine, float volume, std::int32_t number);
     bool rust_sound_mute_update(Engine* engine, bool mute, std::int32_t number);
     bool rust_sound_pan_update(Engine* engine, float pan, std::int32_t number);
+    bool rust_sound_output_update(Engine* engine, std::int32_t channel, std::int32_t number);
+    bool rust_sound_azimuth_update(Engine* engine, float azimuth, std::int32_t number);
     bool rust_sound_bpm_update(Engine* engine, float bpm);
+    bool rust_tempo_set_event(Engine* engine, uint64_t frame, float bpm, bool ramp);
+    bool rust_tempo_remove_event(Engine* engine, uint64_t frame);
+    bool rust_tempo_clear(Engine* engine);
+    uint32_t rust_tempo_event_count(const Engine* engine);
+    uint64_t rust_tempo_event_frame(const Engine* engine, uint32_t index);
+    float rust_tempo_event_bpm(const Engine* engine, uint32_t index);
+    bool rust_tempo_event_ramp(const Engine* engine, uint32_t index);
+    float rust_tempo_bpm_at(const Engine* engine, uint64_t frame);
+    bool rust_time_set_signature(Engine* engine, uint32_t num, uint32_t den);
+    uint32_t rust_time_signature_num(const Engine* engine);
+    uint32_t rust_time_signature_den(const Engine* engine);
+    double rust_time_frames_to_beats(const Engine* engine, uint64_t frame);
+    uint64_t rust_time_beats_to_frames(const Engine* engine, double beats);
+    double rust_time_frames_to_seconds(const Engine* engine, uint64_t frame);
+    uint64_t rust_time_seconds_to_frames(const Engine* engine, double secs);
+    uint32_t rust_time_bar_at(const Engine* engine, uint64_t frame);
+    uint32_t rust_time_beat_at(const Engine* engine, uint64_t frame);
+    uint32_t rust_time_tick_at(const Engine* engine, uint64_t frame);
+    uint64_t rust_time_bbt_to_frames(const Engine* engine, uint32_t bar, uint32_t beat, uint32_t tick);
+    uint64_t rust_time_snap(const Engine* engine, uint64_t frame, uint32_t division);
+
+    bool rust_edit_undo(Engine* engine);
+    bool rust_edit_redo(Engine* engine);
+    bool rust_edit_can_undo(Engine* engine);
+    bool rust_edit_can_redo(Engine* engine);
+    bool rust_edit_begin_group(Engine* engine);
+    bool rust_edit_end_group(Engine* engine);
 
     size_t rust_render_interleaved(Engine* engine, float* inter, size_t frames, uint32_t ch);
     void rust_engine_set_sr(Engine* engine, uint32_t sr);
+    bool rust_engine_set_host_render(Engine* engine, bool on);
+    bool rust_engine_set_ring_latency_ms(Engine* engine, uint32_t ms);
+    uint64_t rust_engine_ring_frames(Engine* engine);
+    bool rust_engine_set_output_layout(Engine* engine, uint32_t layout);
+    uint32_t rust_engine_output_channels(Engine* engine);
+    bool rust_render_offline_to_wav(Engine* engine, const char* path, uint64_t start, uint64_t end, uint32_t bit_depth);
+    OfflineRender* rust_offline_render_begin(Engine* engine, uint64_t start, uint64_t end, uint32_t layout);
+    size_t rust_offline_render_next(OfflineRender* render, float* out, size_t frames);
+    void rust_offline_render_free(OfflineRender* render);
 
     uint64_t rust_transport_pos(Engine* engine);
     uint32_t rust_transport_sr (Engine* engine);
     bool rust_transport_is_playing(Engine* engine);
     bool rust_sound_seek(Engine* engine, uint64_t s);
+    bool rust_transport_set_loop(Engine* engine, uint64_t start, uint64_t end, bool enabled);
+    bool rust_transport_set_loop_enabled(Engine* engine, bool enabled);
+    bool rust_transport_loop_enabled(const Engine* engine);
+    uint64_t rust_transport_loop_start(const Engine* engine);
+    uint64_t rust_transport_loop_end(const Engine* engine);
     uint32_t rust_audio_params_out_sr(Engine* engine);
     uint32_t rust_audio_params_out_bs(Engine* engine);
 
@@ -77,13 +154,24 @@ public:
     void resized() override;
 
     void rust_start_sound(bool bstart);
-    bool rust_file_update(int32_t number, const char* path, uint64_t tl_start, uint64_t tl_len, uint32_t src);
-    bool rust_file_move(int32_t old_track, uint64_t old_start, int32_t new_track, uint64_t new_start);
-    bool rust_file_delet(int32_t track, uint64_t start);
+    uint64_t rust_file_update(int32_t number, const char* path, uint64_t tl_start, uint64_t tl_len, uint32_t src,
+                              uint64_t src_start = 0, uint64_t src_end = 0);
+    bool rust_file_move(uint64_t clip, int32_t new_track, uint64_t new_start);
+    bool rust_file_delet(uint64_t clip);
+    bool rust_file_range(uint64_t clip, uint64_t& start, uint64_t& len);
+    int rust_get_track_count();
+    bool rust_track_clear(int tracknum);
     bool rust_volume_update(float volume , int tracknum);
     bool rust_mute_update(bool muted , int tracknum);
     bool rust_pan_update(float pan, int tracknum);
     bool rust_bpm_update(float bpm);
+    bool rust_group_begin(); // 이 사이의 편집/믹서 변경은 되돌리기 한 번에
+    bool rust_group_end();
+    // 박/마디 격자는 엔진이 계산 (템포 맵, 박자표 반영), 마디/박은 1부터
+    uint32_t rust_meter_num();
+    uint32_t rust_bar_at(uint64_t frame);
+    uint64_t rust_bbt_to_frames(uint32_t bar, uint32_t beat, uint32_t tick = 0);
+    uint64_t rust_snap(uint64_t frame, uint32_t division);
     uint64_t rust_get_pos();
     uint32_t rust_get_sr();
     bool rust_get_is_playing();
@@ -101,6 +189,7 @@ public:
     std::shared_ptr<SoundCore::soundVecterData> audioTrack_1;
     std::shared_ptr<SoundCore::soundVecterData> audioTrack_2;
     std::shared_ptr<SoundCore::soundVecterData> audioTrack_3;
+    std::function<void()> onSampleRateChanged; // 엔진이 클립 위치를 새 레이트로 바꾼 뒤 (메시지 스레드)
     EnginePtr eng;
 
     std::unique_ptr<AudioHostController> host_ = nullptr;
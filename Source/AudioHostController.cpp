@@ -83,6 +83,7 @@ void AudioHostController::audioDeviceStopped()
     this->interBuf_.clear();
     this->interBuf_.shrink_to_fit(); //resize
     lastTick = 0;
+    if (onStopped) onStopped();
 }
 
 void AudioHostController::audioDeviceIOCallbackWithContext(
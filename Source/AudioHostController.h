@@ -46,6 +46,7 @@ public:
                                                                                             //frames is BS  one callback frames
                                                                                            //channels is mono streotype
     std::function<void(double sr, int bs, int ch)> onAboutToStart;
+    std::function<void()> onStopped;
     explicit AudioHostController(renderFn fn = {});
     ~AudioHostController() override;
     
@@ -22,6 +22,8 @@ struct ClipData {
 
     uint64_t startProjFrames = 0;
     uint64_t lenProjFrames = 0;
+
+    uint64_t engineId = 0; // 엔진이 붙인 클립 id (0 = 엔진에 없음)
     static constexpr double kBaseBpm = 60.0;
 
     ClipData(juce::AudioFormatManager& fm,
@@ -42,11 +42,27 @@ MainComponent::MainComponent()
         audioEngine->rust_sample_add(path);
         };
 #pragma endregion
+#pragma region SampleRate callBack
+    audioEngine->onSampleRateChanged = [this]()
+        {
+            // 출력 샘플레이트가 바뀌면 엔진이 환산한 클립 위치/길이로 UI 클립을 다시 맞춤
+            for (int t = 0; t < MainTrack::laneCount; ++t) {
+                for (auto* c : clips[t]) {
+                    uint64_t start = 0, len = 0;
+                    if (audioEngine->rust_file_range(c->engineId, start, len)) {
+                        c->startProjFrames = start;
+                        c->lenProjFrames = len;
+                    }
+                }
+                repaintTrack(t);
+            }
+        };
+#pragma endregion
 #pragma region FileDrepped callBack
     mainTrack.onDropIntoSubTrack = [this](int track, const juce::File& file, float laneX)
         {
-            const double s = timeline.xToSamples(laneX);
-            const uint64_t startProj = (uint64_t)timeline.snapSamples(s, 4);
+            const double s = juce::jmax(0.0, timeline.xToSamples(laneX));
+            const uint64_t startProj = audioEngine->rust_snap((uint64_t)s, 4);
             addClipToTrack(track, file, startProj);
         };
 #pragma endregion
@@ -64,50 +80,21 @@ MainComponent::MainComponent()
 #pragma region TrackClear
     mainTrack.handleMenuSelection = [this](int selectedId) 
         {
-            switch (selectedId)
-            {
-            case 1:
-                DBG("0 Delete");
-                mainTrack_0->fileNames.clear();
-                mainTrack_0->filePaths.clear();
-                mainTrack_0->soundWaveForm.clear();
-                repaint();
-                if (audioEngine) {
-                    DBG("[Rust]-file_all_delete");
-                }
-                break;
-            case 2:
-                DBG("1 Delete");
-                mainTrack_1->fileNames.clear();
-                mainTrack_1->filePaths.clear();
-                mainTrack_1->soundWaveForm.clear();
-                repaint();
-                if (audioEngine) {
-                    DBG("[Rust]-file_all_delete");
-                }
-                break;
-            case 3:
-                DBG("2 Delete");
-                mainTrack_2->fileNames.clear();
-                mainTrack_2->filePaths.clear();
-                mainTrack_2->soundWaveForm.clear();
-                repaint();
-                if (audioEngine) {
-                    DBG("[Rust]-file_all_delete");
-                }
-                break;
-            case 4:
-                DBG("3 Delete");
-                mainTrack_3->fileNames.clear();
-                mainTrack_3->filePaths.clear();
-                mainTrack_3->soundWaveForm.clear();
-                repaint();
-                if (audioEngine) {
-                    DBG("[Rust]-file_all_delete");
-                }
-                break;
-            default:
-                break;
+            const int track = selectedId - 1;
+            std::shared_ptr<SoundCore::soundVecterData> data[] = { mainTrack_0, mainTrack_1, mainTrack_2, mainTrack_3 };
+            if (track < 0 || track >= MainTrack::laneCount) return;
+
+            DBG(juce::String(track) + " Delete");
+            data[track]->fileNames.clear();
+            data[track]->filePaths.clear();
+            data[track]->soundWaveForm.clear();
+            if (selectedTrack == track) {
+                selectedClip = nullptr; selectedTrack = -1; isDraggingClip = false;
+            }
+            clips[track].clear();
+            repaint();
+            if (audioEngine && audioEngine->rust_track_clear(track)) {
+                DBG("[Rust]-file_all_delete");
             }
         };
 #pragma endregion
@@ -192,7 +179,7 @@ MainComponent::MainComponent()
         if (newBpm > 0.0f && audioEngine->rust_bpm_update(newBpm)) {
             timeline.bpm = newBpm;
             const double outSR = (double)audioEngine->rust_get_out_sr();
-            for (int t = 0; t < 4; ++t) {
+            for (int t = 0; t < MainTrack::laneCount; ++t) {
                 for (auto* c : clips[t]) c->recalcProjectFrames(outSR, newBpm);
                 repaintTrack(t);
 
@@ -243,12 +230,27 @@ MainComponent::MainComponent()
         }
         };
 #pragma endregion
+#pragma region Mixer_Drag_Undo_Group
+    // 볼륨 슬라이더/팬 노브를 한 번 끄는 동안 바뀐 값은 되돌리기 한 단계로 묶음
+    for (juce::Slider* s : { &mainTrack.subTrackController_0->slider, &mainTrack.subTrackController_1->slider,
+                             &mainTrack.subTrackController_2->slider, &mainTrack.subTrackController_3->slider,
+                             &mixers.trackMixer_0->volumeKnob, &mixers.trackMixer_1->volumeKnob,
+                             &mixers.trackMixer_2->volumeKnob, &mixers.trackMixer_3->volumeKnob }) {
+        s->onDragStart = [this]() { audioEngine->rust_group_begin(); };
+        s->onDragEnd = [this]() { audioEngine->rust_group_end(); };
+    }
+#pragma endregion
 #pragma region bind_Data
     mainTrack.subTrack_0->bindTimeline(&timeline);
     mainTrack.subTrack_1->bindTimeline(&timeline);
     mainTrack.subTrack_2->bindTimeline(&timeline);
     mainTrack.subTrack_3->bindTimeline(&timeline);
 
+    mainTrack.subTrack_0->bindEngine(audioEngine.get());
+    mainTrack.subTrack_1->bindEngine(audioEngine.get());
+    mainTrack.subTrack_2->bindEngine(audioEngine.get());
+    mainTrack.subTrack_3->bindEngine(audioEngine.get());
+
     mainTrack.subTrack_0->bindClips(&clips[0]);
     mainTrack.subTrack_1->bindClips(&clips[1]);
     mainTrack.subTrack_2->bindClips(&clips[2]);
@@ -413,8 +415,8 @@ void MainComponent::mouseDrag(const juce::MouseEvent& e)
 
     const double sNow = timeline.xToSamples(localX);
     double newStart = sNow - dragGrabOffsetS;
-    newStart = timeline.snapSamples(newStart, 4);
     newStart = std::max(0.0, newStart);
+    newStart = (double)audioEngine->rust_snap((uint64_t)newStart, 4);
     if (hitTrack != selectedTrack)
     {
         const int oldTrack = selectedTrack;
@@ -439,7 +441,7 @@ void MainComponent::mouseUp(const juce::MouseEvent&)
         const uint64_t newStart = dragNewStart;
 
         if (!(dragOrigTrack == newTrack && dragOrigStart == newStart)) {
-            const bool ok = audioEngine && audioEngine->rust_file_move(dragOrigTrack, dragOrigStart, dragNewTrack, dragNewStart);
+            const bool ok = audioEngine && audioEngine->rust_file_move(selectedClip->engineId, dragNewTrack, dragNewStart);
             if (!ok) {
                 DBG("[Rust]-File-Move : Err");
                 const int newTrackWas = selectedTrack;
@@ -480,9 +482,7 @@ void MainComponent::mouseDown(const juce::MouseEvent& e)
         if (idx >= 0)
         {
             ClipData* victim = clips[hitTrack][idx];
-            const uint64_t victimStart = victim->startProjFrames;
-
-            if (audioEngine && audioEngine->rust_file_delet(hitTrack, victimStart)) {
+            if (audioEngine && audioEngine->rust_file_delet(victim->engineId)) {
 
                 clips[hitTrack].remove(idx);              // OwnedArray is RAW delete
                 if (selectedClip == victim) {             // Selecteding the delet
@@ -491,7 +491,7 @@ void MainComponent::mouseDown(const juce::MouseEvent& e)
                 repaintTrack(hitTrack); //track 
             }
             else {
-                DBG("[Rust]_sound_delete_clip_by_start failed");
+                DBG("[Rust]_sound_delete_clip failed");
             }
             return; // 우클릭은 여기서 끝
         }
@@ -526,8 +526,8 @@ bool MainComponent::keyPressed(const juce::KeyPress& key)
 {
     if (key.getTextCharacter() == 'q' || key.getTextCharacter() == 'w' ||
         key.getTextCharacter() == 'e' || key.getTextCharacter() == 'r') { audioEngine->rust_sample_play(); }
-    if (key.getTextCharacter() == '+') timeline.pxPerBeat = juce::jmin(800.0, timeline.pxPerBeat * 1.2);
-    if (key.getTextCharacter() == '-') timeline.pxPerBeat = juce::jmax(10.0, timeline.pxPerBeat / 1.2);
+    if (key.getTextCharacter() == '+') timeline.pxPerSecond = juce::jmin(800.0, timeline.pxPerSecond * 1.2);
+    if (key.getTextCharacter() == '-') timeline.pxPerSecond = juce::jmax(10.0, timeline.pxPerSecond / 1.2);
 
     if (key.getKeyCode() == juce::KeyPress::spaceKey)
     {
@@ -559,7 +559,8 @@ bool MainComponent::keyPressed(const juce::KeyPress& key)
 
 void MainComponent::addClipToTrack(int track, const juce::File& file, uint64_t startSamples)
 {
-    if (track < 0 || track >= 4) return;
+    if (track < 0 || track >= MainTrack::laneCount) return;
+    if (!audioEngine || track >= audioEngine->rust_get_track_count()) return;
     if (!file.existsAsFile())     return;
 
     std::unique_ptr<juce::AudioFormatReader> r(audioShared.fm.createReaderFor(file));
@@ -582,20 +583,24 @@ void MainComponent::addClipToTrack(int track, const juce::File& file, uint64_t s
     // 3) 타임라인 배치(드랍 위치는 '프로젝트 프레임' 단위)
     c->startProjFrames = startSamples;
 
-    clips[track].add(c);
-
-    // 4) 엔진에도 프로젝트 좌표로 전달
-    if (audioEngine) {
-        const juce::String pathStr = file.getFullPathName();
-        const char* path = pathStr.toRawUTF8();
-        audioEngine->rust_file_update(
-            track,
-            path,
-            /*startProjectFrames*/ c->startProjFrames,
-            /*lenProjectFrames*/   c->lenProjFrames,
-            /*srcSR*/              srcSR
-        );
+    // 4) 엔진에도 프로젝트 좌표로 전달, 받은 id로만 이후 편집 (0 = 실패면 UI에도 안 넣음)
+    const juce::String pathStr = file.getFullPathName();
+    const char* path = pathStr.toRawUTF8();
+    c->engineId = audioEngine->rust_file_update(
+        track,
+        path,
+        /*startProjectFrames*/ c->startProjFrames,
+        /*lenProjectFrames*/   c->lenProjFrames,
+        /*srcSR*/              srcSR,
+        /*srcStart*/           c->startSrcSamples,
+        /*srcEnd*/             c->startSrcSamples + c->lenSrcSamples
+    );
+    if (c->engineId == 0) {
+        DBG("[File_Update]-Error");
+        delete c;
+        return;
     }
+    clips[track].add(c);
 
     repaintTrack(track);
 }
@@ -613,7 +618,7 @@ void MainComponent::mouseWheelMove(const juce::MouseEvent& e, const juce::MouseW
 {
     if (e.mods.isCtrlDown()) {
      
-        timeline.pxPerBeat = juce::jlimit(1.0, 2000.0, timeline.pxPerBeat * (1.0 + w.deltaY * 0.2));
+        timeline.pxPerSecond = juce::jlimit(1.0, 2000.0, timeline.pxPerSecond * (1.0 + w.deltaY * 0.2));
     }
     if (e.mods.isShiftDown()) {
         // 가로 스크롤(원하는 감도값으로 조절)
@@ -69,7 +69,7 @@ private:
     float insertionX = 0.0f;
     AudioShared audioShared;
 
-    juce::OwnedArray<ClipData> clips[4];
+    juce::OwnedArray<ClipData> clips[MainTrack::laneCount];
     ClipData* selectedClip = nullptr;
     int       selectedTrack = -1;
     bool      isDraggingClip = false;
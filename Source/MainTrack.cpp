@@ -138,6 +138,10 @@ void MainTrack::mouseDown(const juce::MouseEvent& event)
 
     menu.clear();                      
     menu.addItem(1, "export Wav");
+    juce::PopupMenu clearMenu;
+    for (int t = 0; t < laneCount; ++t)
+        clearMenu.addItem(100 + t + 1, "Track " + juce::String(t + 1));
+    menu.addSubMenu("Clear Track", clearMenu);
 
     const auto screenPt = event.getScreenPosition();
     const juce::Rectangle<int> anchor(screenPt.x, screenPt.y, 1, 1);
@@ -146,5 +150,6 @@ void MainTrack::mouseDown(const juce::MouseEvent& event)
         juce::PopupMenu::Options().withTargetScreenArea(anchor),
         [this](int choice) {
             if (choice == 1 && onExportWav) onExportWav();
+            else if (choice > 100 && handleMenuSelection) handleMenuSelection(choice - 100);
         });
 }
\ No newline at end of file
@@ -26,6 +26,7 @@ public:
     std::function<void(int trackIndex, const juce::File& file, float laneLocalX)>onDropIntoSubTrack;
     std::function<void(int)> handleMenuSelection;
     std::function<void()> onExportWav;
+    static constexpr int laneCount = 4; // 화면에 있는 트랙 레인 수 (엔진 초기 트랙 수)
     void mouseDown(const juce::MouseEvent& event) override;
     void paint (juce::Graphics&) override;
     void resized() override;
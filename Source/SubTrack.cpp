@@ -10,6 +10,7 @@
 
 #include <JuceHeader.h>
 #include "SubTrack.h"
+#include "AudioEngine.h"
 
 //==============================================================================
 SubTrack::SubTrack()
@@ -32,7 +33,7 @@ void SubTrack::paint (juce::Graphics& g)
 {
     g.drawImage(subTrackBackGround, getLocalBounds().toFloat());
 
-    if (timeline != nullptr)
+    if (timeline != nullptr && audioEngine != nullptr)
         drawBeatGrid(g, getLocalBounds());
 
     if (clips && timeline)
@@ -97,41 +98,49 @@ void SubTrack::resized()
 
 void SubTrack::drawBeatGrid(juce::Graphics& g, juce::Rectangle<int> area)
 {
-    const double spb = timeline->samplesPerBeat();
-    const double s0 = timeline->xToSamples((float)area.getX());
+    // / ġ  ڿ  ( ȭ, ǥ и   ڸ)
+    const double s0 = juce::jmax(0.0, timeline->xToSamples((float)area.getX()));
     const double s1 = timeline->xToSamples((float)area.getRight());
-    const long long b0 = (long long)std::floor(s0 / spb) - 1;
-    const long long b1 = (long long)std::ceil(s1 / spb) + 1;
+    const uint32_t num = juce::jmax<uint32_t>(1, audioEngine->rust_meter_num());
 
-    const double pxPerBar = timeline->pxPerBeat * timeline->num; // num=ڼ( 4)
-    const bool showLabels = pxPerBar > 40.0;
-    const bool showSub = pxPerBar > 20.0;
-
-    for (long long b = b0; b <= b1; ++b)
+    for (uint32_t bar = juce::jmax<uint32_t>(1, audioEngine->rust_bar_at((uint64_t)s0));; ++bar)
     {
-        const bool isBar = (timeline->num > 0) ? ((b % timeline->num) == 0) : (b == 0);
-        const float x = timeline->samplesToX((double)b * spb);
-
-        if (isBar) {
-            g.setColour(juce::Colours::white.withAlpha(0.20f));
-            g.drawVerticalLine((int)std::round(x), area.getY(), area.getBottom());
-            if (showLabels) {
-                g.setColour(juce::Colours::white.withAlpha(0.9f));
-                g.drawText("Bar " + juce::String((int)(b / timeline->num) + 1),
-                    (int)x + 3, area.getY() + 2, 60, 16, juce::Justification::left, false);
+        const double barStart = (double)audioEngine->rust_bbt_to_frames(bar, 1);
+        const double barEnd = (double)audioEngine->rust_bbt_to_frames(bar + 1, 1);
+        if (barStart > s1 || barEnd <= barStart) break;
+
+        const double pxPerBar = (barEnd - barStart) / timeline->samplesPerPixel();
+        const bool showLabels = pxPerBar > 40.0;
+        const bool showSub = pxPerBar > 20.0;
+
+        for (uint32_t beat = 1; beat <= num; ++beat)
+        {
+            const double b0 = (beat == 1) ? barStart : (double)audioEngine->rust_bbt_to_frames(bar, beat);
+            const double b1 = (beat == num) ? barEnd : (double)audioEngine->rust_bbt_to_frames(bar, beat + 1);
+            const float x = timeline->samplesToX(b0);
+
+            if (beat == 1) {
+                g.setColour(juce::Colours::white.withAlpha(0.20f));
+                g.drawVerticalLine((int)std::round(x), area.getY(), area.getBottom());
+                if (showLabels) {
+                    g.setColour(juce::Colours::white.withAlpha(0.9f));
+                    g.drawText("Bar " + juce::String((int)bar),
+                        (int)x + 3, area.getY() + 2, 60, 16, juce::Justification::left, false);
+                }
+            }
+            else {
+                g.setColour(juce::Colours::white.withAlpha(0.10f));
+                g.drawVerticalLine((int)std::round(x), area.getY(), area.getBottom());
             }
-        }
-        else {
-            g.setColour(juce::Colours::white.withAlpha(0.10f));
-            g.drawVerticalLine((int)std::round(x), area.getY(), area.getBottom());
-        }
 
-        if (showSub) {
-            for (int k = 1; k < 4; ++k) {
-                const double subs = (double)b * spb + k * (spb / 4.0);
-                const float xs = timeline->samplesToX(subs);
-                g.setColour(juce::Colours::white.withAlpha(0.06f));
-                g.drawVerticalLine((int)std::round(xs), area.getY(), area.getBottom());
+            if (showSub) {
+                //   4  (       )
+                for (int k = 1; k < 4; ++k) {
+                    const double subs = b0 + k * ((b1 - b0) / 4.0);
+                    const float xs = timeline->samplesToX(subs);
+                    g.setColour(juce::Colours::white.withAlpha(0.06f));
+                    g.drawVerticalLine((int)std::round(xs), area.getY(), area.getBottom());
+                }
             }
         }
     }
@@ -15,6 +15,8 @@
 #include "TimeLineState.h"
 #include "AssetsPath.h"
 
+class AudioEngine;
+
 class SubTrack  : public juce::Component 
 {
 public:
@@ -25,6 +27,7 @@ public:
     juce::Array<juce::Image>* soundTrackImg =nullptr;
 #pragma region FileDrep
     void bindTimeline(TimeLine::timeLineState* tl) { timeline = tl; }
+    void bindEngine(AudioEngine* e) { audioEngine = e; }
     void bindClips(juce::OwnedArray<ClipData>* list) { clips = list; }
     float getLastClickX() const { return lastClickX; }
     void mouseDown(const juce::MouseEvent& e) override { lastClickX = e.position.x; }
@@ -36,6 +39,7 @@ private:
     void drawBeatGrid(juce::Graphics& g, juce::Rectangle<int> area);
 
     TimeLine::timeLineState* timeline = nullptr;
+    AudioEngine* audioEngine = nullptr; // 박/마디 격자
     juce::OwnedArray<ClipData>* clips = nullptr;
     float lastClickX = 0.0f;
 #pragma endregion
@@ -15,17 +15,11 @@ namespace TimeLine
     struct timeLineState {
         double sr = 48000.0;
         double bpm = 60.0;
-        int    num = 4, den = 4;
-        double pxPerBeat = 80.0;
+        double pxPerSecond = 80.0; // 확대 배율 (박/마디 위치와 스냅은 엔진 격자: AudioEngine::rust_bbt_to_frames / rust_snap)
         double scrollSamples = 0.0;
 
-        double samplesPerBeat()   const { return sr * 60.0 / bpm; }
-        double samplesPerPixel()  const { return samplesPerBeat() / pxPerBeat; }
+        double samplesPerPixel()  const { return sr / pxPerSecond; }
         double xToSamples(float x)const { return scrollSamples + x * samplesPerPixel(); }
         float  samplesToX(double s)const { return float((s - scrollSamples) / samplesPerPixel()); }
-        double snapSamples(double s, int gridDiv = 4) const {
-            const double g = samplesPerBeat() / double(gridDiv);
-            return std::round(s / g) * g;
-        }
     };
 }
\ No newline at end of file